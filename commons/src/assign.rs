@@ -0,0 +1,217 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Debug;
+use std::hash::Hash;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AssignError<K: Debug, V: Debug> {
+    #[error("No distinct value left for {0:?}")]
+    Unsatisfiable(Vec<K>),
+    #[error("{key:?} could be either {first:?} or {second:?}")]
+    Ambiguous { key: K, first: V, second: V },
+}
+
+/// Finds the one way of giving every key a distinct value from its
+/// candidates.
+///
+/// Keys with a single candidate are pinned and their value removed from
+/// everyone else until nothing is left. If that stalls, what remains is
+/// handed to a bipartite matching, both to confirm there's exactly one
+/// answer and to say which keys are stuck or ambiguous when there isn't.
+pub fn assign<K, V, I, C>(candidates: I) -> Result<HashMap<K, V>, AssignError<K, V>>
+where
+    K: Eq + Hash + Clone + Debug,
+    V: Eq + Hash + Clone + Debug,
+    I: IntoIterator<Item = (K, C)>,
+    C: IntoIterator<Item = V>,
+{
+    let mut remaining: HashMap<K, HashSet<V>> = candidates
+        .into_iter()
+        .map(|(k, vs)| (k, vs.into_iter().collect()))
+        .collect();
+    let mut assigned = HashMap::with_capacity(remaining.len());
+
+    let mut singles: VecDeque<K> = remaining
+        .iter()
+        .filter(|(_, vs)| vs.len() == 1)
+        .map(|(k, _)| k.clone())
+        .collect();
+    while let Some(key) = singles.pop_front() {
+        let value = match remaining.remove(&key) {
+            Some(vs) => match vs.into_iter().next() {
+                Some(v) => v,
+                None => return Err(AssignError::Unsatisfiable(vec![key])),
+            },
+            // Already pinned via another route
+            None => continue,
+        };
+
+        for (other, vs) in remaining.iter_mut() {
+            if vs.remove(&value) {
+                match vs.len() {
+                    0 => return Err(AssignError::Unsatisfiable(vec![other.clone()])),
+                    1 => singles.push_back(other.clone()),
+                    _ => {}
+                }
+            }
+        }
+        assigned.insert(key, value);
+    }
+
+    if !remaining.is_empty() {
+        assigned.extend(match_remaining(remaining)?);
+    }
+    Ok(assigned)
+}
+
+struct Matching<V> {
+    adjacency: Vec<Vec<V>>,
+    owner: HashMap<V, usize>,
+}
+
+impl<V> Matching<V>
+where
+    V: Eq + Hash + Clone,
+{
+    // Kuhn's augmenting path search, skipping the value `banned` for `key`
+    fn augment(&mut self, key: usize, banned: Option<&V>, visited: &mut HashSet<V>) -> bool {
+        for i in 0..self.adjacency[key].len() {
+            let value = self.adjacency[key][i].clone();
+            if Some(&value) == banned || visited.contains(&value) {
+                continue;
+            }
+            visited.insert(value.clone());
+            let free = match self.owner.get(&value) {
+                Some(&other) => self.augment(other, None, visited),
+                None => true,
+            };
+            if free {
+                self.owner.insert(value, key);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+fn match_remaining<K, V>(
+    remaining: HashMap<K, HashSet<V>>,
+) -> Result<HashMap<K, V>, AssignError<K, V>>
+where
+    K: Eq + Hash + Clone + Debug,
+    V: Eq + Hash + Clone + Debug,
+{
+    let (keys, adjacency): (Vec<K>, Vec<Vec<V>>) = remaining
+        .into_iter()
+        .map(|(k, vs)| (k, vs.into_iter().collect()))
+        .unzip();
+    let mut matching = Matching {
+        adjacency,
+        owner: HashMap::new(),
+    };
+
+    let unmatched: Vec<K> = (0..keys.len())
+        .filter(|&k| !matching.augment(k, None, &mut HashSet::new()))
+        .map(|k| keys[k].clone())
+        .collect();
+    if !unmatched.is_empty() {
+        return Err(AssignError::Unsatisfiable(unmatched));
+    }
+
+    let mut matched: Vec<Option<V>> = vec![None; keys.len()];
+    for (v, k) in &matching.owner {
+        matched[*k] = Some(v.clone());
+    }
+    let matched: Vec<V> = matched.into_iter().map(|v| v.unwrap()).collect();
+
+    // The matching is only unique if no key can be moved off its value while
+    // still leaving everyone else matched.
+    for (k, value) in matched.iter().enumerate() {
+        let mut trial = Matching {
+            adjacency: matching.adjacency.clone(),
+            owner: matching.owner.clone(),
+        };
+        trial.owner.remove(value);
+        if trial.augment(k, Some(value), &mut HashSet::new()) {
+            let second = trial
+                .owner
+                .iter()
+                .find(|(_, owner)| **owner == k)
+                .map(|(v, _)| v.clone())
+                .unwrap();
+            return Err(AssignError::Ambiguous {
+                key: keys[k].clone(),
+                first: value.clone(),
+                second,
+            });
+        }
+    }
+
+    Ok(keys.into_iter().zip(matched).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elimination() {
+        // The allergen example from 2020 day 21
+        let candidates = vec![
+            ("dairy", vec!["mxmxvkd"]),
+            ("fish", vec!["mxmxvkd", "sqjhc"]),
+            ("soy", vec!["sqjhc", "fvjkl"]),
+        ];
+        let found = assign(candidates).unwrap();
+        assert_eq!(found.len(), 3);
+        assert_eq!(found["dairy"], "mxmxvkd");
+        assert_eq!(found["fish"], "sqjhc");
+        assert_eq!(found["soy"], "fvjkl");
+    }
+
+    #[test]
+    fn ambiguous() {
+        let candidates = vec![('a', vec![1, 2]), ('b', vec![1, 2]), ('c', vec![1, 2, 3])];
+        match assign(candidates) {
+            Err(AssignError::Ambiguous { key, first, second }) => {
+                // 'c' has to take 3, it's 'a' and 'b' that can swap
+                assert!(key == 'a' || key == 'b');
+                assert_ne!(first, second);
+                assert!(first <= 2 && second <= 2);
+            }
+            other => panic!("Expected ambiguity, got {:?}", other),
+        }
+
+        // A spare value gives 'b' a choice once 'a' is pinned
+        let candidates = vec![('a', vec![1]), ('b', vec![1, 2, 3])];
+        assert!(matches!(
+            assign(candidates),
+            Err(AssignError::Ambiguous { key: 'b', .. })
+        ));
+    }
+
+    #[test]
+    fn unsatisfiable() {
+        let candidates = vec![('a', vec![1]), ('b', vec![1])];
+        assert!(matches!(
+            assign(candidates),
+            Err(AssignError::Unsatisfiable(_))
+        ));
+
+        let candidates = vec![('a', vec![1, 2]), ('b', vec![1, 2]), ('c', vec![1, 2])];
+        let err = assign(candidates).unwrap_err();
+        assert!(matches!(err, AssignError::Unsatisfiable(ref keys) if keys.len() == 1));
+    }
+
+    #[test]
+    fn error_messages() {
+        let err: AssignError<char, u32> = AssignError::Ambiguous {
+            key: 'a',
+            first: 1,
+            second: 2,
+        };
+        assert_eq!(err.to_string(), "'a' could be either 1 or 2");
+        let err: AssignError<char, u32> = AssignError::Unsatisfiable(vec!['b']);
+        assert_eq!(err.to_string(), "No distinct value left for ['b']");
+    }
+}
@@ -0,0 +1,585 @@
+use std::collections::hash_map;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::{Chain, FromIterator};
+use std::ops::Index;
+use std::slice;
+
+/// A fixed-capacity buffer which overwrites its oldest value once full.
+/// Indexing and iteration both run from oldest to newest.
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+    buf: Vec<T>,
+    capacity: usize,
+    insert_at: usize,
+}
+
+impl<T> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        RingBuffer {
+            buf: Vec::with_capacity(capacity),
+            capacity,
+            insert_at: 0,
+        }
+    }
+
+    /// Adds a value, returning whichever value it replaced once the buffer
+    /// is full. With no capacity at all, the value is handed straight back.
+    pub fn push(&mut self, val: T) -> Option<T> {
+        if self.capacity == 0 {
+            return Some(val);
+        }
+        let replaced = if let Some(elem) = self.buf.get_mut(self.insert_at) {
+            Some(std::mem::replace(elem, val))
+        } else {
+            self.buf.push(val);
+            None
+        };
+        self.insert_at = (self.insert_at + 1) % self.capacity;
+        replaced
+    }
+
+    // Position of the oldest value in `buf`
+    fn start(&self) -> usize {
+        if self.is_full() {
+            self.insert_at
+        } else {
+            0
+        }
+    }
+
+    pub fn get(&self, i: usize) -> Option<&T> {
+        if i < self.buf.len() {
+            self.buf.get((self.start() + i) % self.buf.len())
+        } else {
+            None
+        }
+    }
+
+    pub fn oldest(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn newest(&self) -> Option<&T> {
+        self.buf.len().checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buf.len() == self.capacity
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.insert_at = 0;
+    }
+
+    pub fn iter(&self) -> Chain<slice::Iter<'_, T>, slice::Iter<'_, T>> {
+        let (newer, older) = self.buf.split_at(self.start());
+        older.iter().chain(newer.iter())
+    }
+}
+
+impl<T> Index<usize> for RingBuffer<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        match self.get(i) {
+            Some(v) => v,
+            None => panic!(
+                "index {} out of range for ring buffer of length {}",
+                i,
+                self.len()
+            ),
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a RingBuffer<T> {
+    type Item = &'a T;
+    type IntoIter = Chain<slice::Iter<'a, T>, slice::Iter<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Remembers the last `n` values pushed into it. `get` and indexing count
+/// back from the newest value, while iteration runs from oldest to newest.
+#[derive(Debug, Clone)]
+pub struct MostRecent<T> {
+    ring: RingBuffer<T>,
+}
+
+impl<T> MostRecent<T> {
+    pub fn new(n: usize) -> MostRecent<T> {
+        MostRecent {
+            ring: RingBuffer::new(n),
+        }
+    }
+
+    pub fn push(&mut self, val: T) -> Option<T> {
+        self.ring.push(val)
+    }
+
+    /// The `i`th most recent value, where 0 is the newest.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.ring
+            .len()
+            .checked_sub(i + 1)
+            .and_then(|pos| self.ring.get(pos))
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ring.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+
+    pub fn iter(&self) -> Chain<slice::Iter<'_, T>, slice::Iter<'_, T>> {
+        self.ring.iter()
+    }
+}
+
+impl<T> Index<usize> for MostRecent<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        match self.get(i) {
+            Some(v) => v,
+            None => panic!(
+                "index {} out of range for most recent of length {}",
+                i,
+                self.len()
+            ),
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a MostRecent<T> {
+    type Item = &'a T;
+    type IntoIter = Chain<slice::Iter<'a, T>, slice::Iter<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Keeps the last `n` values inserted for each key.
+#[derive(Debug, Clone)]
+pub struct RecencyMap<K, V> {
+    map: HashMap<K, MostRecent<V>>,
+    n: usize,
+}
+
+impl<K, V> RecencyMap<K, V>
+where
+    K: Eq + Hash,
+{
+    pub fn new(n: usize) -> RecencyMap<K, V> {
+        RecencyMap {
+            map: HashMap::new(),
+            n,
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let n = self.n;
+        self.map
+            .entry(key)
+            .or_insert_with(|| MostRecent::new(n))
+            .push(value)
+    }
+
+    /// The `nth` most recent value for `key`, where 0 is the newest.
+    pub fn get(&self, key: &K, nth: usize) -> Option<&V> {
+        self.map.get(key).and_then(|recent| recent.get(nth))
+    }
+
+    pub fn recent(&self, key: &K) -> Option<&MostRecent<V>> {
+        self.map.get(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn iter(&self) -> hash_map::Iter<'_, K, MostRecent<V>> {
+        self.map.iter()
+    }
+}
+
+impl<K, V> Index<&K> for RecencyMap<K, V>
+where
+    K: Eq + Hash,
+{
+    type Output = MostRecent<V>;
+
+    fn index(&self, key: &K) -> &MostRecent<V> {
+        self.map.get(key).expect("key not in recency map")
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a RecencyMap<K, V> {
+    type Item = (&'a K, &'a MostRecent<V>);
+    type IntoIter = hash_map::Iter<'a, K, MostRecent<V>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.iter()
+    }
+}
+
+// Marks a label which isn't currently in the ring
+const UNLINKED: usize = usize::MAX;
+
+/// A circular singly linked list over dense `usize` labels, stored as a table
+/// of each label's successor. Moving runs of labels around is constant time
+/// however big the ring gets. Build one by collecting labels in ring order.
+#[derive(Debug, Clone, Default)]
+pub struct LinkedRing {
+    next: Vec<usize>,
+    head: Option<usize>,
+    len: usize,
+}
+
+impl LinkedRing {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, label: usize) -> bool {
+        self.next(label).is_some()
+    }
+
+    /// The label `Display` starts from, if the ring isn't empty.
+    pub fn head(&self) -> Option<usize> {
+        self.head
+    }
+
+    pub fn set_head(&mut self, label: usize) {
+        assert!(self.contains(label), "label {} not in ring", label);
+        self.head = Some(label);
+    }
+
+    /// The label after `label`, if `label` is in the ring.
+    pub fn next(&self, label: usize) -> Option<usize> {
+        self.next.get(label).copied().filter(|&n| n != UNLINKED)
+    }
+
+    /// Removes the `n` labels following `after`, returning them in ring
+    /// order. Stops short rather than removing `after` itself.
+    ///
+    /// Panics if `after` isn't in the ring.
+    pub fn splice_out(&mut self, after: usize, n: usize) -> Vec<usize> {
+        assert!(self.contains(after), "label {} not in ring", after);
+        let n = n.min(self.len - 1);
+        let mut removed = Vec::with_capacity(n);
+        let mut cursor = self.next[after];
+        for _ in 0..n {
+            removed.push(cursor);
+            let following = self.next[cursor];
+            self.next[cursor] = UNLINKED;
+            if self.head == Some(cursor) {
+                self.head = Some(after);
+            }
+            cursor = following;
+        }
+        self.next[after] = cursor;
+        self.len -= n;
+        removed
+    }
+
+    /// Links `labels`, in order, into the ring straight after `after`.
+    ///
+    /// Panics if `after` isn't in the ring, or if any of `labels` already
+    /// is or appears twice.
+    pub fn insert_after(&mut self, after: usize, labels: &[usize]) {
+        assert!(self.contains(after), "label {} not in ring", after);
+        let mut prev = after;
+        let end = self.next[after];
+        for &label in labels {
+            self.reserve_label(label);
+            self.next[prev] = label;
+            // Linking each label as soon as it's checked means a repeat
+            // later in `labels` is caught too
+            self.next[label] = end;
+            self.len += 1;
+            prev = label;
+        }
+    }
+
+    // Makes room for `label` in the table, checking it isn't in use
+    fn reserve_label(&mut self, label: usize) {
+        if label >= self.next.len() {
+            self.next.resize(label + 1, UNLINKED);
+        }
+        assert!(
+            self.next[label] == UNLINKED,
+            "label {} already in ring",
+            label
+        );
+    }
+
+    /// Every label once, starting from `label` and following the links.
+    /// Empty if `label` isn't in the ring.
+    pub fn iter_from(&self, label: usize) -> LinkedRingIter<'_> {
+        LinkedRingIter {
+            ring: self,
+            next: if self.contains(label) {
+                Some(label)
+            } else {
+                None
+            },
+            start: label,
+        }
+    }
+
+    pub fn iter(&self) -> LinkedRingIter<'_> {
+        match self.head {
+            Some(head) => self.iter_from(head),
+            None => LinkedRingIter {
+                ring: self,
+                next: None,
+                start: 0,
+            },
+        }
+    }
+}
+
+impl FromIterator<usize> for LinkedRing {
+    /// Panics if a label appears more than once.
+    fn from_iter<I: IntoIterator<Item = usize>>(labels: I) -> Self {
+        let mut ring = LinkedRing::new();
+        let mut last = None;
+        for label in labels {
+            ring.reserve_label(label);
+            match last {
+                Some(prev) => ring.next[prev] = label,
+                None => ring.head = Some(label),
+            }
+            // Closes the ring each time, so it's never left dangling
+            ring.next[label] = ring.head.unwrap();
+            ring.len += 1;
+            last = Some(label);
+        }
+        ring
+    }
+}
+
+impl fmt::Display for LinkedRing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, label) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", label)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct LinkedRingIter<'a> {
+    ring: &'a LinkedRing,
+    next: Option<usize>,
+    start: usize,
+}
+
+impl<'a> Iterator for LinkedRingIter<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let label = self.next?;
+        let following = self.ring.next[label];
+        self.next = if following == self.start {
+            None
+        } else {
+            Some(following)
+        };
+        Some(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_buffer() {
+        let mut ring = RingBuffer::new(3);
+        assert!(ring.is_empty());
+        assert_eq!(ring.oldest(), None);
+        assert_eq!(ring.newest(), None);
+
+        assert_eq!(ring.push(1), None);
+        assert_eq!(ring.push(2), None);
+        assert_eq!(ring.iter().collect::<Vec<_>>(), vec![&1, &2]);
+        assert_eq!(ring.push(3), None);
+        assert!(ring.is_full());
+        assert_eq!(ring.push(4), Some(1));
+        assert_eq!(ring.push(5), Some(2));
+
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.capacity(), 3);
+        assert_eq!(ring.iter().collect::<Vec<_>>(), vec![&3, &4, &5]);
+        assert_eq!(ring.iter().rev().collect::<Vec<_>>(), vec![&5, &4, &3]);
+        assert_eq!((&ring).into_iter().sum::<u32>(), 12);
+        assert_eq!(ring[0], 3);
+        assert_eq!(ring[2], 5);
+        assert_eq!(ring.get(3), None);
+        assert_eq!(ring.oldest(), Some(&3));
+        assert_eq!(ring.newest(), Some(&5));
+
+        ring.clear();
+        assert!(ring.is_empty());
+        ring.push(6);
+        assert_eq!(ring.iter().collect::<Vec<_>>(), vec![&6]);
+    }
+
+    #[test]
+    fn ring_buffer_no_capacity() {
+        let mut ring = RingBuffer::new(0);
+        assert_eq!(ring.push(1), Some(1));
+        assert!(ring.is_empty());
+        assert_eq!(ring.iter().count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn ring_buffer_out_of_range() {
+        let mut ring = RingBuffer::new(2);
+        ring.push(1);
+        let _ = ring[1];
+    }
+
+    #[test]
+    fn most_recent() {
+        let mut recent = MostRecent::new(2);
+        recent.push('a');
+        assert_eq!(recent.get(0), Some(&'a'));
+        assert_eq!(recent.get(1), None);
+        recent.push('b');
+        assert_eq!(recent.push('c'), Some('a'));
+        assert_eq!(recent[0], 'c');
+        assert_eq!(recent[1], 'b');
+        assert_eq!(recent.get(2), None);
+        assert_eq!(recent.iter().collect::<String>(), "bc");
+    }
+
+    #[test]
+    fn recency_map() {
+        let mut map = RecencyMap::new(2);
+        map.insert("x", 1);
+        map.insert("y", 2);
+        map.insert("x", 3);
+        assert_eq!(map.insert("x", 4), Some(1));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&"x", 0), Some(&4));
+        assert_eq!(map.get(&"x", 1), Some(&3));
+        assert_eq!(map.get(&"x", 2), None);
+        assert_eq!(map.get(&"z", 0), None);
+        assert!(map.contains_key(&"y"));
+        assert_eq!(map[&"y"].iter().collect::<Vec<_>>(), vec![&2]);
+        assert_eq!(map.recent(&"x").map(|r| r.len()), Some(2));
+
+        let mut totals: Vec<(&str, i32)> = map.iter().map(|(k, r)| (*k, r.iter().sum())).collect();
+        totals.sort_unstable();
+        assert_eq!(totals, vec![("x", 7), ("y", 2)]);
+    }
+
+    #[test]
+    fn linked_ring() {
+        // The cups example from 2020 day 23
+        let mut ring: LinkedRing = vec![3, 8, 9, 1, 2, 5, 4, 6, 7].into_iter().collect();
+        assert_eq!(ring.len(), 9);
+        assert_eq!(ring.to_string(), "3 8 9 1 2 5 4 6 7");
+        assert_eq!(ring.next(7), Some(3));
+        assert_eq!(ring.next(10), None);
+
+        let picked = ring.splice_out(3, 3);
+        assert_eq!(picked, vec![8, 9, 1]);
+        assert_eq!(ring.len(), 6);
+        assert!(!ring.contains(8));
+        assert_eq!(ring.to_string(), "3 2 5 4 6 7");
+
+        ring.insert_after(2, &picked);
+        assert_eq!(ring.to_string(), "3 2 8 9 1 5 4 6 7");
+        assert_eq!(
+            ring.iter_from(1).collect::<Vec<_>>(),
+            vec![1, 5, 4, 6, 7, 3, 2, 8, 9]
+        );
+        assert_eq!(ring.iter_from(42).count(), 0);
+
+        ring.insert_after(7, &[12]);
+        assert_eq!(ring.len(), 10);
+        assert_eq!(ring.next(12), Some(3));
+        ring.set_head(12);
+        assert_eq!(ring.to_string(), "12 3 2 8 9 1 5 4 6 7");
+    }
+
+    #[test]
+    fn linked_ring_edges() {
+        let mut ring: LinkedRing = vec![0, 1, 2].into_iter().collect();
+        // Splicing out the head moves it back to where the splice started
+        assert_eq!(ring.splice_out(2, 1), vec![0]);
+        assert_eq!(ring.head(), Some(2));
+        assert_eq!(ring.to_string(), "2 1");
+        // Never removes the label being spliced after
+        assert_eq!(ring.splice_out(1, 5), vec![2]);
+        assert_eq!(ring.to_string(), "1");
+        assert_eq!(ring.next(1), Some(1));
+
+        let empty = LinkedRing::new();
+        assert!(empty.is_empty());
+        assert_eq!(empty.iter().count(), 0);
+        assert_eq!(empty.to_string(), "");
+    }
+
+    #[test]
+    #[should_panic]
+    fn linked_ring_duplicate() {
+        let mut ring: LinkedRing = vec![1, 2].into_iter().collect();
+        ring.insert_after(1, &[2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "label 5 already in ring")]
+    fn linked_ring_repeated_insert() {
+        let mut ring: LinkedRing = vec![1, 2].into_iter().collect();
+        ring.insert_after(1, &[5, 5]);
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Where an iterated sequence starts repeating: the state at step
+/// `start + length` is the same as the one at `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// The earliest step with the same state as step `n`.
+    pub fn equivalent_step(&self, n: usize) -> usize {
+        if n < self.start {
+            n
+        } else {
+            self.start + (n - self.start) % self.length
+        }
+    }
+}
+
+/// Floyd's tortoise and hare. Only ever holds three states, but steps
+/// through the sequence around three times. Returns the cycle along with the
+/// state at its start.
+///
+/// `step` has to be deterministic, and the sequence has to cycle eventually
+/// or this never returns.
+pub fn floyd<S, F>(initial: S, mut step: F) -> (Cycle, S)
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    let mut tortoise = step(&initial);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        let halfway = step(&hare);
+        hare = step(&halfway);
+    }
+
+    // The distance from the meeting point to the cycle start is the same as
+    // from the beginning
+    let mut start = 0;
+    tortoise = initial;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    let mut length = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        length += 1;
+    }
+
+    (Cycle { start, length }, tortoise)
+}
+
+/// Brent's algorithm. Finds the same cycle as `floyd`, usually in fewer
+/// steps, by moving the tortoise up to the hare at each power of two.
+pub fn brent<S, F>(initial: S, mut step: F) -> (Cycle, S)
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = step(&initial);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = step(&hare);
+        length += 1;
+    }
+
+    // Start the hare a cycle's length ahead, and they'll meet at the start
+    let mut start = 0;
+    tortoise = initial.clone();
+    hare = initial;
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        start += 1;
+    }
+
+    (Cycle { start, length }, tortoise)
+}
+
+/// Remembers every key it's shown, for sequences that can't be restarted or
+/// where something cheaper than the whole state identifies it.
+#[derive(Debug, Clone)]
+pub struct CycleDetector<K> {
+    seen: HashMap<K, usize>,
+    steps: usize,
+}
+
+impl<K> CycleDetector<K>
+where
+    K: Eq + Hash,
+{
+    pub fn new() -> Self {
+        CycleDetector {
+            seen: HashMap::new(),
+            steps: 0,
+        }
+    }
+
+    /// Records the key for the next step, returning the cycle if it's been
+    /// seen before. Repeats aren't recorded, so the step count stays put.
+    pub fn observe(&mut self, key: K) -> Option<Cycle> {
+        if let Some(&start) = self.seen.get(&key) {
+            return Some(Cycle {
+                start,
+                length: self.steps - start,
+            });
+        }
+        self.seen.insert(key, self.steps);
+        self.steps += 1;
+        None
+    }
+
+    /// How many distinct keys have been observed.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
+
+impl<K> Default for CycleDetector<K>
+where
+    K: Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The first cycle in a sequence of keys, or `None` if it ends first.
+pub fn find_cycle<I>(keys: I) -> Option<Cycle>
+where
+    I: IntoIterator,
+    I::Item: Eq + Hash,
+{
+    let mut detector = CycleDetector::new();
+    keys.into_iter().find_map(|key| detector.observe(key))
+}
+
+/// The state after `n` steps, skipping whole laps of the cycle once one
+/// turns up.
+pub fn fast_forward<S, F>(initial: S, mut step: F, n: usize) -> S
+where
+    S: Clone + Eq + Hash,
+    F: FnMut(&S) -> S,
+{
+    let mut history = vec![initial.clone()];
+    let mut detector = CycleDetector::new();
+    detector.observe(initial);
+    while history.len() <= n {
+        let next = step(history.last().unwrap());
+        if let Some(cycle) = detector.observe(next.clone()) {
+            return history.swap_remove(cycle.equivalent_step(n));
+        }
+        history.push(next);
+    }
+    history.swap_remove(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3 -> 10 -> 101 -> 2 -> 5 -> 26 -> 167 -> 95 -> 101
+    fn next(x: &u32) -> u32 {
+        (x * x + 1) % 255
+    }
+
+    fn nth(initial: u32, n: usize) -> u32 {
+        (0..n).fold(initial, |x, _| next(&x))
+    }
+
+    #[test]
+    fn pointer_chasing() {
+        let expected = Cycle {
+            start: 2,
+            length: 6,
+        };
+        assert_eq!(floyd(3, next), (expected, 101));
+        assert_eq!(brent(3, next), (expected, 101));
+
+        // Already on the cycle
+        assert_eq!(floyd(101, next).0.start, 0);
+        assert_eq!(brent(101, next).0.start, 0);
+
+        // A fixed point
+        let settle = |x: &u32| x / 2;
+        let fixed = Cycle {
+            start: 7,
+            length: 1,
+        };
+        assert_eq!(floyd(100, settle), (fixed, 0));
+        assert_eq!(brent(100, settle), (fixed, 0));
+    }
+
+    #[test]
+    fn hashed() {
+        let states = std::iter::successors(Some(3), |x| Some(next(x)));
+        assert_eq!(
+            find_cycle(states),
+            Some(Cycle {
+                start: 2,
+                length: 6
+            })
+        );
+        assert_eq!(find_cycle(vec![1, 2, 3]), None);
+
+        let mut detector = CycleDetector::new();
+        assert_eq!(detector.observe('a'), None);
+        assert_eq!(detector.observe('b'), None);
+        assert_eq!(
+            detector.observe('b'),
+            Some(Cycle {
+                start: 1,
+                length: 1
+            })
+        );
+        assert_eq!(detector.steps(), 2);
+    }
+
+    #[test]
+    fn skipping_ahead() {
+        let cycle = Cycle {
+            start: 2,
+            length: 6,
+        };
+        assert_eq!(cycle.equivalent_step(0), 0);
+        assert_eq!(cycle.equivalent_step(7), 7);
+        assert_eq!(cycle.equivalent_step(8), 2);
+
+        for n in 0..40 {
+            assert_eq!(fast_forward(3, next, n), nth(3, n));
+        }
+        assert_eq!(
+            fast_forward(3, next, 1_000_000_000),
+            nth(3, 2 + 999_999_998 % 6)
+        );
+    }
+}
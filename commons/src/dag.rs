@@ -0,0 +1,359 @@
+use crate::search::reachable;
+use petgraph::graph::{Graph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{Add, Mul};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DagError<K: Debug> {
+    #[error("Unknown node {0:?}")]
+    UnknownNode(K),
+    #[error("Cycle found through {0:?}")]
+    Cycle(Vec<K>),
+}
+
+/// A directed graph addressed by key rather than node index. Edges point
+/// from parent to child and carry a weight; adding the same edge twice sums
+/// the weights.
+#[derive(Debug, Clone)]
+pub struct KeyedDag<K, W> {
+    graph: Graph<K, W>,
+    ids: HashMap<K, NodeIndex>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    InProgress,
+    Done,
+}
+
+impl<K, W> KeyedDag<K, W>
+where
+    K: Eq + Hash + Clone + Debug,
+    W: Copy + Default + Add<Output = W> + Mul<Output = W>,
+{
+    pub fn new() -> Self {
+        KeyedDag {
+            graph: Graph::new(),
+            ids: HashMap::new(),
+        }
+    }
+
+    pub fn add_node(&mut self, key: &K) -> NodeIndex {
+        match self.ids.entry(key.clone()) {
+            Entry::Occupied(o) => *o.get(),
+            Entry::Vacant(v) => *v.insert(self.graph.add_node(key.clone())),
+        }
+    }
+
+    pub fn add_edge(&mut self, parent: &K, child: &K, weight: W) {
+        let parent_id = self.add_node(parent);
+        let child_id = self.add_node(child);
+        match self.graph.find_edge(parent_id, child_id) {
+            Some(edge_idx) => {
+                let existing = self.graph.edge_weight_mut(edge_idx).unwrap();
+                *existing = *existing + weight;
+            }
+            None => {
+                self.graph.add_edge(parent_id, child_id, weight);
+            }
+        }
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.ids.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graph.node_count() == 0
+    }
+
+    fn index(&self, key: &K) -> Result<NodeIndex, DagError<K>> {
+        self.ids
+            .get(key)
+            .copied()
+            .ok_or_else(|| DagError::UnknownNode(key.clone()))
+    }
+
+    fn linked(&self, idx: NodeIndex, dir: Direction) -> Vec<(&K, &W)> {
+        self.graph
+            .edges_directed(idx, dir)
+            .map(|e| {
+                let other = match dir {
+                    Direction::Outgoing => e.target(),
+                    Direction::Incoming => e.source(),
+                };
+                (&self.graph[other], e.weight())
+            })
+            .collect()
+    }
+
+    pub fn children(&self, key: &K) -> Result<Vec<(&K, &W)>, DagError<K>> {
+        Ok(self.linked(self.index(key)?, Direction::Outgoing))
+    }
+
+    pub fn parents(&self, key: &K) -> Result<Vec<(&K, &W)>, DagError<K>> {
+        Ok(self.linked(self.index(key)?, Direction::Incoming))
+    }
+
+    fn closure(&self, key: &K, dir: Direction) -> Result<HashSet<&K>, DagError<K>> {
+        let idx = self.index(key)?;
+        let found = reachable(&idx, |n| {
+            self.graph
+                .neighbors_directed(*n, dir)
+                .collect::<Vec<NodeIndex>>()
+        });
+        Ok(found.into_iter().map(|n| &self.graph[n]).collect())
+    }
+
+    /// Every node that can reach `key`.
+    pub fn ancestors(&self, key: &K) -> Result<HashSet<&K>, DagError<K>> {
+        self.closure(key, Direction::Incoming)
+    }
+
+    /// Every node reachable from `key`.
+    pub fn descendants(&self, key: &K) -> Result<HashSet<&K>, DagError<K>> {
+        self.closure(key, Direction::Outgoing)
+    }
+
+    // Depth-first post-order over the whole graph, or the first cycle found
+    fn post_order(&self) -> Result<Vec<NodeIndex>, Vec<NodeIndex>> {
+        self.post_order_from(self.graph.node_indices())
+    }
+
+    // As `post_order`, but only over what can be reached from `roots`
+    fn post_order_from(
+        &self,
+        roots: impl IntoIterator<Item = NodeIndex>,
+    ) -> Result<Vec<NodeIndex>, Vec<NodeIndex>> {
+        let mut state: HashMap<NodeIndex, Visit> = HashMap::new();
+        let mut order = Vec::new();
+        for root in roots {
+            if state.contains_key(&root) {
+                continue;
+            }
+            // Each stack entry is a node and its children yet to be visited
+            let mut stack = vec![(root, self.graph.neighbors(root).collect::<Vec<_>>())];
+            state.insert(root, Visit::InProgress);
+            while let Some((node, pending)) = stack.last_mut() {
+                let node = *node;
+                match pending.pop() {
+                    Some(child) => match state.get(&child) {
+                        Some(Visit::Done) => {}
+                        Some(Visit::InProgress) => {
+                            let start = stack.iter().position(|(n, _)| *n == child).unwrap();
+                            let mut cycle: Vec<NodeIndex> =
+                                stack[start..].iter().map(|(n, _)| *n).collect();
+                            cycle.push(child);
+                            return Err(cycle);
+                        }
+                        None => {
+                            state.insert(child, Visit::InProgress);
+                            stack.push((child, self.graph.neighbors(child).collect()));
+                        }
+                    },
+                    None => {
+                        state.insert(node, Visit::Done);
+                        order.push(node);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    fn keys_for(&self, nodes: Vec<NodeIndex>) -> Vec<K> {
+        nodes.into_iter().map(|n| self.graph[n].clone()).collect()
+    }
+
+    /// A cycle in the graph if there is one, listed from the first node
+    /// around and back to it again.
+    pub fn find_cycle(&self) -> Option<Vec<K>> {
+        self.post_order().err().map(|c| self.keys_for(c))
+    }
+
+    /// All nodes ordered so that every parent comes before its children.
+    pub fn topological_order(&self) -> Result<Vec<&K>, DagError<K>> {
+        match self.post_order() {
+            Ok(order) => Ok(order.into_iter().rev().map(|n| &self.graph[n]).collect()),
+            Err(cycle) => Err(DagError::Cycle(self.keys_for(cycle))),
+        }
+    }
+
+    // The descendant weight of every node in `order`, which must list
+    // children before their parents
+    fn weights(&self, order: &[NodeIndex]) -> HashMap<NodeIndex, W> {
+        let mut memo: HashMap<NodeIndex, W> = HashMap::with_capacity(order.len());
+        for node in order {
+            let total = self.graph.edges_directed(*node, Direction::Outgoing).fold(
+                W::default(),
+                |acc, e| {
+                    let w = *e.weight();
+                    acc + w + w * memo[&e.target()]
+                },
+            );
+            memo.insert(*node, total);
+        }
+        memo
+    }
+
+    /// Total weight of everything below `key`, where each child counts for
+    /// its edge weight multiplied by one plus its own descendant weight.
+    /// This is the "how many bags are inside this bag" count. Only a cycle
+    /// reachable from `key` is an error.
+    pub fn descendant_weight(&self, key: &K) -> Result<W, DagError<K>> {
+        let target = self.index(key)?;
+        let order = self
+            .post_order_from(std::iter::once(target))
+            .map_err(|cycle| DagError::Cycle(self.keys_for(cycle)))?;
+        Ok(self.weights(&order)[&target])
+    }
+
+    /// The descendant weight of every node at once, for when many are
+    /// needed.
+    pub fn descendant_weights(&self) -> Result<HashMap<&K, W>, DagError<K>> {
+        let order = self
+            .post_order()
+            .map_err(|cycle| DagError::Cycle(self.keys_for(cycle)))?;
+        Ok(self
+            .weights(&order)
+            .into_iter()
+            .map(|(n, w)| (&self.graph[n], w))
+            .collect())
+    }
+}
+
+impl<K, W> Default for KeyedDag<K, W>
+where
+    K: Eq + Hash + Clone + Debug,
+    W: Copy + Default + Add<Output = W> + Mul<Output = W>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bags() -> KeyedDag<&'static str, usize> {
+        // The example rules from 2020 day 7
+        let mut dag = KeyedDag::new();
+        dag.add_edge(&"light red", &"bright white", 1);
+        dag.add_edge(&"light red", &"muted yellow", 2);
+        dag.add_edge(&"dark orange", &"bright white", 3);
+        dag.add_edge(&"dark orange", &"muted yellow", 4);
+        dag.add_edge(&"bright white", &"shiny gold", 1);
+        dag.add_edge(&"muted yellow", &"shiny gold", 2);
+        dag.add_edge(&"muted yellow", &"faded blue", 9);
+        dag.add_edge(&"shiny gold", &"dark olive", 1);
+        dag.add_edge(&"shiny gold", &"vibrant plum", 2);
+        dag.add_edge(&"dark olive", &"faded blue", 3);
+        dag.add_edge(&"dark olive", &"dotted black", 4);
+        dag.add_edge(&"vibrant plum", &"faded blue", 5);
+        dag.add_edge(&"vibrant plum", &"dotted black", 6);
+        dag
+    }
+
+    #[test]
+    fn ancestors_and_descendants() {
+        let dag = bags();
+        assert_eq!(dag.len(), 9);
+        assert_eq!(dag.ancestors(&"shiny gold").unwrap().len(), 4);
+        let mut below: Vec<_> = dag
+            .descendants(&"shiny gold")
+            .unwrap()
+            .into_iter()
+            .collect();
+        below.sort_unstable();
+        assert_eq!(
+            below,
+            vec![
+                &"dark olive",
+                &"dotted black",
+                &"faded blue",
+                &"vibrant plum"
+            ]
+        );
+        assert_eq!(
+            dag.ancestors(&"mystery"),
+            Err(DagError::UnknownNode("mystery"))
+        );
+    }
+
+    #[test]
+    fn weighted_counts() {
+        let dag = bags();
+        assert_eq!(dag.descendant_weight(&"shiny gold"), Ok(32));
+        assert_eq!(dag.descendant_weight(&"faded blue"), Ok(0));
+        assert_eq!(dag.descendant_weight(&"dark olive"), Ok(7));
+
+        let all = dag.descendant_weights().unwrap();
+        assert_eq!(all.len(), 9);
+        assert_eq!(all[&"shiny gold"], 32);
+        assert_eq!(all[&"dark olive"], 7);
+    }
+
+    #[test]
+    fn repeated_edges_sum() {
+        let mut dag = KeyedDag::new();
+        dag.add_edge(&'a', &'b', 2);
+        dag.add_edge(&'a', &'b', 3);
+        assert_eq!(dag.children(&'a').unwrap(), vec![(&'b', &5)]);
+        assert_eq!(dag.parents(&'b').unwrap(), vec![(&'a', &5)]);
+    }
+
+    #[test]
+    fn topological() {
+        let dag = bags();
+        let order = dag.topological_order().unwrap();
+        let position = |k: &str| order.iter().position(|n| **n == k).unwrap();
+        assert!(position("light red") < position("shiny gold"));
+        assert!(position("shiny gold") < position("dark olive"));
+        assert!(position("dark olive") < position("dotted black"));
+        assert_eq!(dag.find_cycle(), None);
+    }
+
+    #[test]
+    fn cycles() {
+        let mut dag = KeyedDag::new();
+        dag.add_edge(&1, &2, 1);
+        dag.add_edge(&2, &3, 1);
+        dag.add_edge(&3, &1, 1);
+        dag.add_edge(&3, &4, 1);
+
+        let cycle = dag.find_cycle().unwrap();
+        assert_eq!(cycle.len(), 4);
+        assert_eq!(cycle.first(), cycle.last());
+        assert!(matches!(dag.topological_order(), Err(DagError::Cycle(_))));
+        assert!(matches!(dag.descendant_weight(&1), Err(DagError::Cycle(_))));
+        assert!(matches!(dag.descendant_weights(), Err(DagError::Cycle(_))));
+        // Nothing below 4 loops, so it doesn't matter that 4's parents do
+        assert_eq!(dag.descendant_weight(&4), Ok(0));
+
+        let mut dag = KeyedDag::new();
+        dag.add_edge(&1, &2, 3);
+        dag.add_edge(&5, &6, 1);
+        dag.add_edge(&6, &5, 1);
+        assert_eq!(dag.descendant_weight(&1), Ok(3));
+        assert_eq!(
+            dag.descendant_weight(&5),
+            Err(DagError::Cycle(vec![5, 6, 5]))
+        );
+        assert_eq!(
+            DagError::Cycle(vec![1, 2, 1]).to_string(),
+            "Cycle found through [1, 2, 1]"
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::iter::FromIterator;
 
 pub trait Grid {
     type Value;
@@ -13,11 +15,114 @@ pub trait Grid {
     fn from_rows(source: impl IntoIterator<Item = impl IntoIterator<Item = Self::Value>>) -> Self;
 }
 
-trait FullGrid: Grid {
-    fn row_for_point(p: &Self::Coordinate) -> usize;
-    fn column_for_point(p: &Self::Coordinate) -> usize;
+pub trait Coordinate: Copy + Eq + Hash {
+    /// Every coordinate adjacent to this one, including diagonals.
+    fn neighbours(&self) -> Vec<Self>;
+    fn component_min(&self, other: &Self) -> Self;
+    fn component_max(&self, other: &Self) -> Self;
 }
 
+macro_rules! impl_tuple_coordinate {
+    ($($t:ty),*) => {
+        $(
+            impl Coordinate for ($t, $t) {
+                fn neighbours(&self) -> Vec<Self> {
+                    let mut a = Vec::with_capacity(8);
+                    for x in -1..=1 {
+                        for y in -1..=1 {
+                            if x == 0 && y == 0 {
+                                continue;
+                            }
+                            a.push((self.0 + x, self.1 + y));
+                        }
+                    }
+                    a
+                }
+
+                fn component_min(&self, other: &Self) -> Self {
+                    (cmp::min(self.0, other.0), cmp::min(self.1, other.1))
+                }
+
+                fn component_max(&self, other: &Self) -> Self {
+                    (cmp::max(self.0, other.0), cmp::max(self.1, other.1))
+                }
+            }
+
+            impl Coordinate for ($t, $t, $t) {
+                fn neighbours(&self) -> Vec<Self> {
+                    let mut a = Vec::with_capacity(26);
+                    for x in -1..=1 {
+                        for y in -1..=1 {
+                            for z in -1..=1 {
+                                if x == 0 && y == 0 && z == 0 {
+                                    continue;
+                                }
+                                a.push((self.0 + x, self.1 + y, self.2 + z));
+                            }
+                        }
+                    }
+                    a
+                }
+
+                fn component_min(&self, other: &Self) -> Self {
+                    (
+                        cmp::min(self.0, other.0),
+                        cmp::min(self.1, other.1),
+                        cmp::min(self.2, other.2),
+                    )
+                }
+
+                fn component_max(&self, other: &Self) -> Self {
+                    (
+                        cmp::max(self.0, other.0),
+                        cmp::max(self.1, other.1),
+                        cmp::max(self.2, other.2),
+                    )
+                }
+            }
+
+            impl Coordinate for ($t, $t, $t, $t) {
+                fn neighbours(&self) -> Vec<Self> {
+                    let mut a = Vec::with_capacity(80);
+                    for x in -1..=1 {
+                        for y in -1..=1 {
+                            for z in -1..=1 {
+                                for w in -1..=1 {
+                                    if x == 0 && y == 0 && z == 0 && w == 0 {
+                                        continue;
+                                    }
+                                    a.push((self.0 + x, self.1 + y, self.2 + z, self.3 + w));
+                                }
+                            }
+                        }
+                    }
+                    a
+                }
+
+                fn component_min(&self, other: &Self) -> Self {
+                    (
+                        cmp::min(self.0, other.0),
+                        cmp::min(self.1, other.1),
+                        cmp::min(self.2, other.2),
+                        cmp::min(self.3, other.3),
+                    )
+                }
+
+                fn component_max(&self, other: &Self) -> Self {
+                    (
+                        cmp::max(self.0, other.0),
+                        cmp::max(self.1, other.1),
+                        cmp::max(self.2, other.2),
+                        cmp::max(self.3, other.3),
+                    )
+                }
+            }
+        )*
+    };
+}
+
+impl_tuple_coordinate!(isize, i64, i32);
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct VecGrid<T> {
     rows: Vec<Vec<T>>,
@@ -43,11 +148,11 @@ impl<T> VecGrid<T> {
     }
 
     pub fn edges(&self) -> Vec<Vec<&T>> {
-        if self.width == None || self.rows.is_empty() {
+        if self.width.is_none() || self.rows.is_empty() {
             // There are all empty edges when we don't have any content
             vec![Vec::new(), Vec::new(), Vec::new(), Vec::new()]
         } else {
-            let top = self.rows.get(0).unwrap().iter().collect();
+            let top = self.rows.first().unwrap().iter().collect();
             let bottom = self.rows.get(self.height() - 1).unwrap().iter().collect();
             let mut right = Vec::with_capacity(self.height());
             let mut left = Vec::with_capacity(self.height());
@@ -59,6 +164,10 @@ impl<T> VecGrid<T> {
             vec![top, right, bottom, left]
         }
     }
+
+    pub fn wrapping(&self, wrap: Wrap) -> WrappingGrid<'_, T> {
+        WrappingGrid::new(self, wrap)
+    }
 }
 
 impl<T> Grid for VecGrid<T> {
@@ -75,7 +184,7 @@ impl<T> Grid for VecGrid<T> {
 
     fn at(&self, coord: &Self::Coordinate) -> Option<&T> {
         let (x, y) = coord;
-        self.rows.get(*y).map(|row| row.get(*x)).flatten()
+        self.rows.get(*y).and_then(|row| row.get(*x))
     }
 
     fn points(&self) -> Vec<(Self::Coordinate, &Self::Value)> {
@@ -96,6 +205,106 @@ impl<T> Grid for VecGrid<T> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    Horizontal,
+    Vertical,
+    Torus,
+}
+
+impl Wrap {
+    fn wraps_x(self) -> bool {
+        matches!(self, Wrap::Horizontal | Wrap::Torus)
+    }
+
+    fn wraps_y(self) -> bool {
+        matches!(self, Wrap::Vertical | Wrap::Torus)
+    }
+}
+
+/// A view over a `VecGrid` that repeats infinitely along the wrapped axes.
+/// Coordinates are signed so that walking off the top or left edge wraps
+/// back around to the bottom or right.
+#[derive(Debug)]
+pub struct WrappingGrid<'a, T> {
+    grid: &'a VecGrid<T>,
+    wrap: Wrap,
+}
+
+impl<'a, T> WrappingGrid<'a, T> {
+    pub fn new(grid: &'a VecGrid<T>, wrap: Wrap) -> Self {
+        WrappingGrid { grid, wrap }
+    }
+
+    pub fn horizontal(grid: &'a VecGrid<T>) -> Self {
+        Self::new(grid, Wrap::Horizontal)
+    }
+
+    pub fn vertical(grid: &'a VecGrid<T>) -> Self {
+        Self::new(grid, Wrap::Vertical)
+    }
+
+    pub fn torus(grid: &'a VecGrid<T>) -> Self {
+        Self::new(grid, Wrap::Torus)
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.grid.height()
+    }
+
+    /// Maps a coordinate onto the underlying grid, or `None` if it falls
+    /// outside an axis that doesn't wrap.
+    pub fn normalise(&self, coord: &(isize, isize)) -> Option<(usize, usize)> {
+        let x = wrap_axis(coord.0, self.width(), self.wrap.wraps_x())?;
+        let y = wrap_axis(coord.1, self.height(), self.wrap.wraps_y())?;
+        Some((x, y))
+    }
+
+    pub fn at(&self, coord: &(isize, isize)) -> Option<&'a T> {
+        let grid = self.grid;
+        self.normalise(coord).and_then(|c| grid.at(&c))
+    }
+
+    /// Walks from `start` in steps of `stride` until leaving the grid. On a
+    /// torus this never ends, so callers will want to `take` from it.
+    pub fn walk(
+        &self,
+        start: (isize, isize),
+        stride: (isize, isize),
+    ) -> impl Iterator<Item = &'a T> + '_ {
+        let mut pos = start;
+        std::iter::from_fn(move || {
+            let val = self.at(&pos)?;
+            pos = (pos.0 + stride.0, pos.1 + stride.1);
+            Some(val)
+        })
+    }
+}
+
+impl<'a, T> Clone for WrappingGrid<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for WrappingGrid<'a, T> {}
+
+fn wrap_axis(v: isize, len: usize, wraps: bool) -> Option<usize> {
+    if len == 0 {
+        None
+    } else if wraps {
+        Some(v.rem_euclid(len as isize) as usize)
+    } else if v >= 0 && (v as usize) < len {
+        Some(v as usize)
+    } else {
+        None
+    }
+}
+
 impl<T> Default for VecGrid<T> {
     fn default() -> Self {
         Self::new()
@@ -103,50 +312,75 @@ impl<T> Default for VecGrid<T> {
 }
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct SparseGrid<T> {
-    cells: HashMap<(isize, isize), T>,
+pub struct SparseGrid<C, T>
+where
+    C: Coordinate,
+{
+    cells: HashMap<C, T>,
 }
 
-impl<T> SparseGrid<T> {
+impl<C, T> SparseGrid<C, T>
+where
+    C: Coordinate,
+{
     pub fn new() -> Self {
         SparseGrid {
             cells: HashMap::new(),
         }
     }
 
-    pub fn set(&mut self, coord: (isize, isize), val: T) {
+    pub fn set(&mut self, coord: C, val: T) {
         self.cells.insert(coord, val);
     }
 
-    fn key_range<F>(&self, key_fn: F) -> usize
-    where
-        F: Fn(isize, isize) -> isize,
-    {
-        if self.cells.is_empty() {
-            0
-        } else {
-            let mut min = isize::MAX;
-            let mut max = isize::MIN;
-            for k in self.cells.keys() {
-                let v = key_fn(k.0, k.1);
-                min = cmp::min(min, v);
-                max = cmp::max(max, v);
-            }
-            (max - min + 1) as usize
-        }
+    pub fn get(&self, coord: &C) -> Option<&T> {
+        self.cells.get(coord)
+    }
+
+    pub fn remove(&mut self, coord: &C) -> Option<T> {
+        self.cells.remove(coord)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&C, &T)> {
+        self.cells.iter()
+    }
+
+    pub fn neighbours(&self, coord: &C) -> impl Iterator<Item = (C, &T)> {
+        coord
+            .neighbours()
+            .into_iter()
+            .filter_map(move |n| self.cells.get(&n).map(|v| (n, v)))
+    }
+
+    pub fn bounds(&self) -> Option<(C, C)> {
+        bounds(self.cells.keys())
     }
 }
 
-impl<T> Grid for SparseGrid<T> {
+impl<T> Grid for SparseGrid<(isize, isize), T> {
     type Value = T;
     type Coordinate = (isize, isize);
 
     fn height(&self) -> usize {
-        self.key_range(|_, y| y)
+        match self.bounds() {
+            Some((min, max)) => (max.1 - min.1 + 1) as usize,
+            None => 0,
+        }
     }
 
     fn width(&self) -> usize {
-        self.key_range(|x, _| x)
+        match self.bounds() {
+            Some((min, max)) => (max.0 - min.0 + 1) as usize,
+            None => 0,
+        }
     }
 
     fn at(&self, coord: &Self::Coordinate) -> Option<&T> {
@@ -168,23 +402,145 @@ impl<T> Grid for SparseGrid<T> {
     }
 }
 
-impl<T> Default for SparseGrid<T> {
+impl<C, T> Default for SparseGrid<C, T>
+where
+    C: Coordinate,
+{
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<T> Clone for SparseGrid<T>
+impl<C, T> Clone for SparseGrid<C, T>
 where
-    T: Clone
+    C: Coordinate,
+    T: Clone,
 {
     fn clone(&self) -> Self {
         SparseGrid {
-            cells: self.cells.clone()
+            cells: self.cells.clone(),
         }
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SparseSet<C>
+where
+    C: Coordinate,
+{
+    cells: HashSet<C>,
+}
+
+impl<C> SparseSet<C>
+where
+    C: Coordinate,
+{
+    pub fn new() -> Self {
+        SparseSet {
+            cells: HashSet::new(),
+        }
+    }
+
+    pub fn insert(&mut self, coord: C) -> bool {
+        self.cells.insert(coord)
+    }
+
+    pub fn remove(&mut self, coord: &C) -> bool {
+        self.cells.remove(coord)
+    }
+
+    /// Flips the given cell, returning whether it is now active.
+    pub fn toggle(&mut self, coord: C) -> bool {
+        if self.cells.remove(&coord) {
+            false
+        } else {
+            self.cells.insert(coord);
+            true
+        }
+    }
+
+    pub fn contains(&self, coord: &C) -> bool {
+        self.cells.contains(coord)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &C> {
+        self.cells.iter()
+    }
+
+    pub fn active_neighbours(&self, coord: &C) -> usize {
+        coord
+            .neighbours()
+            .iter()
+            .filter(|n| self.cells.contains(n))
+            .count()
+    }
+
+    pub fn bounds(&self) -> Option<(C, C)> {
+        bounds(self.cells.iter())
+    }
+
+    /// Runs one generation of a cellular automaton. `rule` is given whether
+    /// a cell is currently active and how many of its neighbours are, and
+    /// returns whether the cell is active in the next generation.
+    pub fn step<F>(&self, rule: F) -> Self
+    where
+        F: Fn(bool, usize) -> bool,
+    {
+        // Only active cells and their neighbours can possibly be active next
+        let mut counts: HashMap<C, usize> = HashMap::new();
+        for cell in &self.cells {
+            counts.entry(*cell).or_insert(0);
+            for neighbour in cell.neighbours() {
+                *counts.entry(neighbour).or_insert(0) += 1;
+            }
+        }
+
+        counts
+            .into_iter()
+            .filter(|(coord, count)| rule(self.cells.contains(coord), *count))
+            .map(|(coord, _)| coord)
+            .collect()
+    }
+}
+
+impl<C> Default for SparseSet<C>
+where
+    C: Coordinate,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> FromIterator<C> for SparseSet<C>
+where
+    C: Coordinate,
+{
+    fn from_iter<I: IntoIterator<Item = C>>(iter: I) -> Self {
+        SparseSet {
+            cells: iter.into_iter().collect(),
+        }
+    }
+}
+
+fn bounds<'a, C>(coords: impl Iterator<Item = &'a C>) -> Option<(C, C)>
+where
+    C: Coordinate + 'a,
+{
+    coords.fold(None, |acc, c| match acc {
+        None => Some((*c, *c)),
+        Some((min, max)) => Some((min.component_min(c), max.component_max(c))),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -228,9 +584,50 @@ mod tests {
         assert_eq!(other_grid, grid);
     }
 
+    #[test]
+    fn wrapping_vecgrid() {
+        let grid = VecGrid::from_rows(vec![vec![0, 1, 2], vec![3, 4, 5]]);
+
+        let horizontal = grid.wrapping(Wrap::Horizontal);
+        assert_eq!(horizontal.at(&(3, 0)), Some(&0));
+        assert_eq!(horizontal.at(&(-1, 1)), Some(&5));
+        assert_eq!(horizontal.at(&(-7, 0)), Some(&2));
+        assert_eq!(horizontal.at(&(0, 2)), None);
+        assert_eq!(horizontal.at(&(0, -1)), None);
+
+        let vertical = WrappingGrid::vertical(&grid);
+        assert_eq!(vertical.at(&(1, 2)), Some(&1));
+        assert_eq!(vertical.at(&(1, -1)), Some(&4));
+        assert_eq!(vertical.at(&(3, 0)), None);
+
+        let torus = WrappingGrid::torus(&grid);
+        assert_eq!(torus.at(&(-1, -1)), Some(&5));
+        assert_eq!(torus.normalise(&(7, 5)), Some((1, 1)));
+    }
+
+    #[test]
+    fn wrapping_walk() {
+        let grid = VecGrid::from_rows(vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8]]);
+
+        let walked: Vec<_> = grid
+            .wrapping(Wrap::Horizontal)
+            .walk((0, 0), (2, 1))
+            .collect();
+        assert_eq!(walked, vec![&0, &5, &7]);
+
+        let walked: Vec<_> = WrappingGrid::torus(&grid)
+            .walk((0, 0), (-1, 1))
+            .take(4)
+            .collect();
+        assert_eq!(walked, vec![&0, &5, &7, &0]);
+
+        let empty = VecGrid::<usize>::new();
+        assert_eq!(empty.wrapping(Wrap::Torus).walk((0, 0), (1, 1)).count(), 0);
+    }
+
     #[test]
     fn empty_sparsegrid() {
-        let grid = SparseGrid::<usize>::new();
+        let grid = SparseGrid::<(isize, isize), usize>::new();
 
         assert_eq!(grid.width(), 0);
         assert_eq!(grid.height(), 0);
@@ -258,5 +655,59 @@ mod tests {
         assert_eq!(grid.at(&(1, 1)), Some(&2));
         assert_eq!(grid.at(&(1, 2)), Some(&4));
         assert_eq!(grid.at(&(4, 2)), None);
+
+        assert_eq!(grid.bounds(), Some(((-1, -1), (1, 2))));
+        let mut neighbours: Vec<_> = grid.neighbours(&(0, 1)).map(|(_, v)| *v).collect();
+        neighbours.sort_unstable();
+        assert_eq!(neighbours, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn sparsegrid_3d() {
+        let mut grid = SparseGrid::new();
+        grid.set((0i64, 0, 0), 'a');
+        grid.set((2, -3, 1), 'b');
+
+        assert_eq!(grid.len(), 2);
+        assert_eq!(grid.get(&(2, -3, 1)), Some(&'b'));
+        assert_eq!(grid.bounds(), Some(((0, -3, 0), (2, 0, 1))));
+        assert_eq!(grid.remove(&(0, 0, 0)), Some('a'));
+        assert_eq!(grid.len(), 1);
+    }
+
+    #[test]
+    fn coordinate_neighbours() {
+        assert_eq!((0isize, 0).neighbours().len(), 8);
+        assert_eq!((0i64, 0, 0).neighbours().len(), 26);
+        assert_eq!((0i32, 0, 0, 0).neighbours().len(), 80);
+        assert!(!(5isize, 5).neighbours().contains(&(5, 5)));
+    }
+
+    #[test]
+    fn sparseset_toggle() {
+        let mut set = SparseSet::new();
+        assert!(set.toggle((1isize, 1)));
+        assert!(set.contains(&(1, 1)));
+        assert!(!set.toggle((1, 1)));
+        assert!(set.is_empty());
+        assert_eq!(set.bounds(), None);
+    }
+
+    #[test]
+    fn sparseset_step() {
+        // The glider-ish example from 2020 day 17
+        let mut set: SparseSet<(i64, i64, i64)> =
+            vec![(1, 0, 0), (2, 1, 0), (0, 2, 0), (1, 2, 0), (2, 2, 0)]
+                .into_iter()
+                .collect();
+        assert_eq!(set.active_neighbours(&(1, 1, 0)), 5);
+
+        let rule = |active, count| matches!((active, count), (true, 2) | (_, 3));
+        set = set.step(rule);
+        assert_eq!(set.len(), 11);
+        for _ in 1..6 {
+            set = set.step(rule);
+        }
+        assert_eq!(set.len(), 112);
     }
 }
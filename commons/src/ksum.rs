@@ -0,0 +1,411 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::hash::Hash;
+use std::ops::Add;
+
+pub trait Summable: Copy + Ord + Hash + Add<Output = Self> {
+    /// What's left of the target is kept in this, so that it can go past
+    /// the ends of `Self` part way through a sum which comes back in range.
+    /// Unsigned types don't need anything wider, as once the target goes
+    /// below zero nothing can bring it back.
+    type Wide: Summable;
+
+    /// `self - other`, or `None` if that can't be represented.
+    fn checked_diff(self, other: Self) -> Option<Self>;
+
+    fn widen(self) -> Self::Wide;
+
+    fn narrow(wide: Self::Wide) -> Option<Self>;
+}
+
+macro_rules! impl_summable {
+    ($($t:ty => $wide:ty),*) => {
+        $(
+            impl Summable for $t {
+                type Wide = $wide;
+
+                fn checked_diff(self, other: Self) -> Option<Self> {
+                    self.checked_sub(other)
+                }
+
+                fn widen(self) -> $wide {
+                    self as $wide
+                }
+
+                fn narrow(wide: $wide) -> Option<Self> {
+                    <$t>::try_from(wide).ok()
+                }
+            }
+        )*
+    };
+}
+
+// Sums of 128-bit values can still go out of range part way through, in
+// which case those combinations are missed
+impl_summable!(
+    u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => usize,
+    i8 => i128, i16 => i128, i32 => i128, i64 => i128, i128 => i128, isize => i128
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Sorts (a copy of) the input and closes in from both ends.
+    Sorted,
+    /// Looks up the final value of each combination in a hash map.
+    Hashed,
+}
+
+/// Finds `k` values from a slice, at distinct positions, that add up to a
+/// target. The input doesn't need to be sorted.
+#[derive(Debug, Clone)]
+pub struct KSum<'a, T> {
+    values: &'a [T],
+    k: usize,
+    strategy: Strategy,
+}
+
+impl<'a, T> KSum<'a, T>
+where
+    T: Summable,
+{
+    pub fn new(values: &'a [T], k: usize) -> Self {
+        KSum {
+            values,
+            k,
+            strategy: Strategy::Sorted,
+        }
+    }
+
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Every combination of `k` indices whose values sum to `target`, each
+    /// in ascending order. The combinations themselves come in no
+    /// particular order.
+    pub fn indices(&self, target: T) -> Vec<Vec<usize>> {
+        let mut found = Vec::new();
+        self.search(target, &mut |combination| {
+            found.push(combination);
+            true
+        });
+        found
+    }
+
+    pub fn first_indices(&self, target: T) -> Option<Vec<usize>> {
+        let mut found = None;
+        self.search(target, &mut |combination| {
+            found = Some(combination);
+            false
+        });
+        found
+    }
+
+    /// As `indices`, but giving the values at those indices.
+    pub fn values(&self, target: T) -> Vec<Vec<T>> {
+        self.indices(target)
+            .into_iter()
+            .map(|c| self.values_at(&c))
+            .collect()
+    }
+
+    pub fn first_values(&self, target: T) -> Option<Vec<T>> {
+        self.first_indices(target).map(|c| self.values_at(&c))
+    }
+
+    fn values_at(&self, indices: &[usize]) -> Vec<T> {
+        indices.iter().map(|&i| self.values[i]).collect()
+    }
+
+    // Calls `found` with each combination until it returns false
+    fn search(&self, target: T, found: &mut dyn FnMut(Vec<usize>) -> bool) {
+        if self.k == 0 || self.k > self.values.len() {
+            return;
+        }
+        let target = target.widen();
+        let mut chosen = Vec::with_capacity(self.k);
+        match self.strategy {
+            Strategy::Sorted => {
+                let mut order: Vec<usize> = (0..self.values.len()).collect();
+                order.sort_by_key(|&i| self.values[i]);
+                let mut report = |positions: &[usize]| {
+                    let mut combination: Vec<usize> = positions.iter().map(|&p| order[p]).collect();
+                    combination.sort_unstable();
+                    found(combination)
+                };
+                let sorted: Vec<T> = order.iter().map(|&i| self.values[i]).collect();
+                Self::search_from(
+                    &sorted,
+                    self.k,
+                    0,
+                    target,
+                    &mut chosen,
+                    &mut report,
+                    self.strategy,
+                );
+            }
+            Strategy::Hashed => {
+                let mut report = |indices: &[usize]| found(indices.to_vec());
+                Self::search_from(
+                    self.values,
+                    self.k,
+                    0,
+                    target,
+                    &mut chosen,
+                    &mut report,
+                    self.strategy,
+                );
+            }
+        }
+    }
+
+    // Picks values one at a time until there's a pair left to find, which is
+    // left to the strategy. Returns false once asked to stop.
+    fn search_from(
+        values: &[T],
+        k: usize,
+        start: usize,
+        rest: T::Wide,
+        chosen: &mut Vec<usize>,
+        found: &mut dyn FnMut(&[usize]) -> bool,
+        strategy: Strategy,
+    ) -> bool {
+        match k {
+            1 => {
+                for (i, v) in values.iter().enumerate().skip(start) {
+                    if v.widen() == rest {
+                        chosen.push(i);
+                        let more = found(chosen);
+                        chosen.pop();
+                        if !more {
+                            return false;
+                        }
+                    }
+                }
+                true
+            }
+            2 => match strategy {
+                Strategy::Sorted => Self::sorted_pairs(values, start, rest, chosen, found),
+                Strategy::Hashed => Self::hashed_pairs(values, start, rest, chosen, found),
+            },
+            _ => {
+                for i in start..=(values.len() - k) {
+                    let next_rest = match rest.checked_diff(values[i].widen()) {
+                        Some(r) => r,
+                        None => continue,
+                    };
+                    chosen.push(i);
+                    let more =
+                        Self::search_from(values, k - 1, i + 1, next_rest, chosen, found, strategy);
+                    chosen.pop();
+                    if !more {
+                        return false;
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    fn sorted_pairs(
+        values: &[T],
+        start: usize,
+        rest: T::Wide,
+        chosen: &mut Vec<usize>,
+        found: &mut dyn FnMut(&[usize]) -> bool,
+    ) -> bool {
+        if values.len() < start + 2 {
+            return true;
+        }
+        let mut lo = start;
+        let mut hi = values.len() - 1;
+        while lo < hi {
+            let (a, b) = (values[lo], values[hi]);
+            // Compare `a` with `rest - b` rather than adding, which can
+            // overflow. A difference that can't be represented is past
+            // whichever end of the range is on the other side of `b`.
+            let order = match rest.checked_diff(b.widen()) {
+                Some(needed) => a.widen().cmp(&needed),
+                None if b.widen() > rest => Ordering::Greater,
+                None => Ordering::Less,
+            };
+            match order {
+                Ordering::Less => lo += 1,
+                Ordering::Greater => hi -= 1,
+                Ordering::Equal => {
+                    // Equal values at either end mean every pairing of the runs
+                    // is a separate answer
+                    let lo_end = if a == b {
+                        hi
+                    } else {
+                        (lo..=hi).find(|&i| values[i] != a).unwrap() - 1
+                    };
+                    let hi_start = if a == b {
+                        lo
+                    } else {
+                        (lo_end + 1..=hi).find(|&i| values[i] == b).unwrap()
+                    };
+                    for i in lo..=lo_end {
+                        for j in hi_start.max(i + 1)..=hi {
+                            chosen.push(i);
+                            chosen.push(j);
+                            let more = found(chosen);
+                            chosen.pop();
+                            chosen.pop();
+                            if !more {
+                                return false;
+                            }
+                        }
+                    }
+                    if a == b {
+                        break;
+                    }
+                    lo = lo_end + 1;
+                    hi = hi_start - 1;
+                }
+            }
+        }
+        true
+    }
+
+    fn hashed_pairs(
+        values: &[T],
+        start: usize,
+        rest: T::Wide,
+        chosen: &mut Vec<usize>,
+        found: &mut dyn FnMut(&[usize]) -> bool,
+    ) -> bool {
+        let mut seen: HashMap<T, Vec<usize>> = HashMap::new();
+        for (j, v) in values.iter().enumerate().skip(start) {
+            let needed = rest.checked_diff(v.widen()).and_then(T::narrow);
+            if let Some(earlier) = needed.and_then(|c| seen.get(&c)) {
+                for &i in earlier {
+                    chosen.push(i);
+                    chosen.push(j);
+                    let more = found(chosen);
+                    chosen.pop();
+                    chosen.pop();
+                    if !more {
+                        return false;
+                    }
+                }
+            }
+            seen.entry(*v).or_default().push(j);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    const EXPENSES: [u32; 6] = [1721, 979, 366, 299, 675, 1456];
+
+    fn brute_force(values: &[i32], k: usize, target: i32) -> Vec<Vec<usize>> {
+        (0..values.len())
+            .combinations(k)
+            .filter(|c| c.iter().map(|&i| values[i]).sum::<i32>() == target)
+            .collect()
+    }
+
+    fn sorted(mut found: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn pairs() {
+        // The expense report example from 2020 day 1
+        for strategy in &[Strategy::Sorted, Strategy::Hashed] {
+            let search = KSum::new(&EXPENSES, 2).strategy(*strategy);
+            assert_eq!(search.first_indices(2020), Some(vec![0, 3]));
+            assert_eq!(search.first_values(2020), Some(vec![1721, 299]));
+            assert_eq!(search.indices(2020), vec![vec![0, 3]]);
+            assert_eq!(search.first_indices(1), None);
+
+            // Sums that don't fit in the type are never the target
+            let bytes = KSum::new(&[200u8, 100, 250], 2).strategy(*strategy);
+            assert_eq!(bytes.indices(255), Vec::<Vec<usize>>::new());
+            assert_eq!(bytes.indices(250), Vec::<Vec<usize>>::new());
+            let big = KSum::new(&[i64::MAX, 1, i64::MIN, -1], 2).strategy(*strategy);
+            assert_eq!(sorted(big.indices(i64::MAX - 1)), vec![vec![0, 3]]);
+            assert_eq!(sorted(big.indices(-1)), vec![vec![0, 2]]);
+        }
+    }
+
+    #[test]
+    fn triples() {
+        for strategy in &[Strategy::Sorted, Strategy::Hashed] {
+            let search = KSum::new(&EXPENSES, 3).strategy(*strategy);
+            assert_eq!(search.values(2020), vec![vec![979, 366, 675]]);
+        }
+    }
+
+    #[test]
+    fn duplicates_and_negatives() {
+        let values = [2, -1, 3, 2, 2, 0, 3];
+        for strategy in &[Strategy::Sorted, Strategy::Hashed] {
+            let search = KSum::new(&values, 2).strategy(*strategy);
+            // Three 2s give three distinct pairs, plus -1 with either 3
+            assert_eq!(
+                sorted(search.indices(4)),
+                vec![vec![0, 3], vec![0, 4], vec![3, 4]]
+            );
+            assert_eq!(
+                sorted(search.indices(2)),
+                vec![vec![0, 5], vec![1, 2], vec![1, 6], vec![3, 5], vec![4, 5]]
+            );
+            for k in 3..=5 {
+                for target in -1..=12 {
+                    let found = KSum::new(&values, k).strategy(*strategy).indices(target);
+                    assert_eq!(sorted(found), brute_force(&values, k, target));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn extreme_values() {
+        // Sums that go out of range part way through but come back in
+        let values = [i64::MIN, i64::MAX, i64::MAX, 0, -1, i64::MIN + 1];
+        let brute_force = |k: usize, target: i64| -> Vec<Vec<usize>> {
+            (0..values.len())
+                .combinations(k)
+                .filter(|c| c.iter().map(|&i| values[i] as i128).sum::<i128>() == target as i128)
+                .collect()
+        };
+        for strategy in &[Strategy::Sorted, Strategy::Hashed] {
+            let search = KSum::new(&values, 3).strategy(*strategy);
+            assert_eq!(
+                sorted(search.indices(i64::MAX - 1)),
+                vec![vec![0, 1, 2], vec![1, 3, 4], vec![2, 3, 4]]
+            );
+            for k in 2..=5 {
+                for &target in &[i64::MIN, -2, -1, 0, 1, i64::MAX - 1, i64::MAX] {
+                    let found = KSum::new(&values, k).strategy(*strategy).indices(target);
+                    assert_eq!(sorted(found), brute_force(k, target), "{} {}", k, target);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate() {
+        let empty: [u64; 0] = [];
+        assert_eq!(KSum::new(&empty, 2).first_indices(0), None);
+        assert_eq!(KSum::new(&[5u8], 2).indices(5), Vec::<Vec<usize>>::new());
+        assert_eq!(KSum::new(&[5u8], 1).indices(5), vec![vec![0]]);
+        assert_eq!(KSum::new(&[5u8, 1], 0).indices(0), Vec::<Vec<usize>>::new());
+        // Unsigned values larger than the target can't be part of a sum
+        assert_eq!(
+            KSum::new(&[200u8, 3, 1, 2], 3)
+                .strategy(Strategy::Hashed)
+                .indices(6),
+            vec![vec![1, 2, 3]]
+        );
+    }
+}
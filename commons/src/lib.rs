@@ -1,6 +1,15 @@
+pub mod assign;
+pub mod collections;
+pub mod cycle;
+pub mod dag;
 pub mod geom;
 pub mod grid;
 pub mod io;
+pub mod ksum;
+pub mod math;
+pub mod paths;
+pub mod search;
+pub mod window;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+// Everything here works on u64/i64 at the edges, but multiplies in u128/i128
+// so that moduli up to u64::MAX can't overflow part way through.
+
+pub fn gcd(a: u64, b: u64) -> u64 {
+    let (mut a, mut b) = (a, b);
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+/// Lowest common multiple, or `None` if it doesn't fit in a u64.
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Returns `(g, x, y)` such that `a * x + b * y == g`, where `g` is the
+/// greatest common divisor of `a` and `b`.
+pub fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    let (g, x, y) = extended_gcd_wide(a as i128, b as i128);
+    (g as i64, x as i64, y as i64)
+}
+
+fn extended_gcd_wide(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (1, 0);
+    let (mut old_t, mut t) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        let next_r = old_r - q * r;
+        old_r = r;
+        r = next_r;
+        let next_s = old_s - q * s;
+        old_s = s;
+        s = next_s;
+        let next_t = old_t - q * t;
+        old_t = t;
+        t = next_t;
+    }
+    if old_r < 0 {
+        (-old_r, -old_s, -old_t)
+    } else {
+        (old_r, old_s, old_t)
+    }
+}
+
+/// The `x` in `0..m` such that `a * x ≡ 1 (mod m)`, if `a` and `m` are coprime.
+pub fn mod_inverse(a: i64, m: u64) -> Option<u64> {
+    mod_inverse_wide(a as i128, m)
+}
+
+fn mod_inverse_wide(a: i128, m: u64) -> Option<u64> {
+    if m == 0 {
+        return None;
+    }
+    let m_wide = m as i128;
+    let (g, x, _) = extended_gcd_wide(a.rem_euclid(m_wide), m_wide);
+    if g != 1 {
+        return None;
+    }
+    Some(x.rem_euclid(m_wide) as u64)
+}
+
+/// `a * b` modulo `m`, or `None` if `m` is zero.
+pub fn mod_mul(a: u64, b: u64, m: u64) -> Option<u64> {
+    (a as u128 * b as u128)
+        .checked_rem(m as u128)
+        .map(|r| r as u64)
+}
+
+/// `base` raised to `exp`, modulo `m`, by repeated squaring. `None` if `m`
+/// is zero.
+pub fn mod_pow(base: u64, exp: u64, m: u64) -> Option<u64> {
+    if m == 1 {
+        return Some(0);
+    }
+    let mut result = 1;
+    let mut base = base.checked_rem(m)?;
+    let mut exp = exp;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mod_mul(result, base, m)?;
+        }
+        base = mod_mul(base, base, m)?;
+        exp >>= 1;
+    }
+    Some(result)
+}
+
+/// Solves a system of congruences `x ≡ residue (mod modulus)`, returning the
+/// smallest non-negative `x` along with the modulus of the combined system.
+///
+/// The moduli don't need to be coprime. Returns `None` if the congruences
+/// contradict each other, or if the combined modulus doesn't fit in a u64.
+pub fn crt(congruences: &[(i64, u64)]) -> Option<(u64, u64)> {
+    let mut residue: i128 = 0;
+    let mut modulus: i128 = 1;
+    for &(r, m) in congruences {
+        if m == 0 {
+            return None;
+        }
+        let m = m as i128;
+        let r = (r as i128).rem_euclid(m);
+
+        // residue + modulus * k ≡ r (mod m), solve for k
+        let (g, inv, _) = extended_gcd_wide(modulus, m);
+        let diff = r - residue;
+        if diff % g != 0 {
+            return None;
+        }
+        let step = m / g;
+        let k = ((diff / g) % step * (inv % step)).rem_euclid(step);
+
+        let combined = modulus.checked_mul(step)?;
+        if combined > u64::MAX as i128 {
+            return None;
+        }
+        residue = (residue + modulus * k).rem_euclid(combined);
+        modulus = combined;
+    }
+    Some((residue as u64, modulus as u64))
+}
+
+/// Baby-step giant-step discrete logarithm: the smallest `x` such that
+/// `base^x ≡ target (mod m)`. `base` must be coprime with `m`. `None` if
+/// there's no such `x`, or if `m` is zero.
+pub fn discrete_log(base: u64, target: u64, m: u64) -> Option<u64> {
+    if m == 1 {
+        return Some(0);
+    }
+    let target = target.checked_rem(m)?;
+    let mut n = (m as f64).sqrt().ceil() as u64;
+    while (n as u128) * (n as u128) < m as u128 {
+        n += 1;
+    }
+
+    // Baby steps: base^j for j in 0..n, keeping the smallest j for each value
+    let mut table = HashMap::with_capacity(n as usize);
+    let mut power = 1;
+    for j in 0..n {
+        table.entry(power).or_insert(j);
+        power = mod_mul(power, base, m)?;
+    }
+
+    // Giant steps: target * base^(-n * i) for i in 0..n
+    let factor = mod_pow(mod_inverse_wide(base as i128, m)?, n, m)?;
+    let mut gamma = target;
+    for i in 0..n {
+        if let Some(j) = table.get(&gamma) {
+            return Some(i * n + j);
+        }
+        gamma = mod_mul(gamma, factor, m)?;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcds() {
+        assert_eq!(gcd(48, 18), 6);
+        assert_eq!(gcd(0, 5), 5);
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(u64::MAX, 2), None);
+
+        let (g, x, y) = extended_gcd(240, 46);
+        assert_eq!(g, 2);
+        assert_eq!(240 * x + 46 * y, 2);
+        let (g, x, y) = extended_gcd(-12, 18);
+        assert_eq!(g, 6);
+        assert_eq!(-12 * x + 18 * y, 6);
+    }
+
+    #[test]
+    fn inverses() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+        assert_eq!(mod_inverse(6, 9), None);
+        assert_eq!(mod_inverse(1, 0), None);
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(mod_pow(4, 13, 497), Some(445));
+        assert_eq!(mod_pow(7, 0, 13), Some(1));
+        assert_eq!(mod_pow(7, 5, 1), Some(0));
+        // Would overflow a u64 multiply without widening
+        let big = (1 << 63) + 25;
+        assert_eq!(mod_pow(big - 1, 2, big), Some(1));
+        assert_eq!(mod_mul(big - 1, big - 1, big), Some(1));
+    }
+
+    #[test]
+    fn zero_modulus() {
+        assert_eq!(mod_mul(3, 4, 0), None);
+        assert_eq!(mod_pow(3, 4, 0), None);
+        assert_eq!(mod_pow(3, 0, 0), None);
+        assert_eq!(mod_inverse(3, 0), None);
+        assert_eq!(crt(&[(1, 0)]), None);
+        assert_eq!(discrete_log(3, 1, 0), None);
+    }
+
+    #[test]
+    fn chinese_remainder() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        // The bus example from 2020 day 13: 7,13,x,x,59,x,31,19
+        let buses = [(0, 7), (-1, 13), (-4, 59), (-6, 31), (-7, 19)];
+        assert_eq!(crt(&buses), Some((1068781, 3162341)));
+        // Non-coprime moduli
+        assert_eq!(crt(&[(2, 4), (4, 6)]), Some((10, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+        assert_eq!(crt(&[]), Some((0, 1)));
+        // Combined modulus too large
+        assert_eq!(crt(&[(0, u64::MAX), (1, 2)]), None);
+    }
+
+    #[test]
+    fn discrete_logs() {
+        // The handshake example from 2020 day 25
+        assert_eq!(discrete_log(7, 5764801, 20201227), Some(8));
+        assert_eq!(discrete_log(7, 17807724, 20201227), Some(11));
+        assert_eq!(discrete_log(2, 1, 13), Some(0));
+        assert_eq!(discrete_log(3, 13, 17), Some(4));
+        // 2 only generates the quadratic residues mod 7
+        assert_eq!(discrete_log(2, 3, 7), None);
+    }
+}
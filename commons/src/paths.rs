@@ -0,0 +1,348 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::mem;
+use std::ops::Add;
+
+// Counts are generic so that callers can pick u128, or a big integer type,
+// when the number of paths outgrows u64. Anything convertible from u8 with
+// an `Add` will do.
+
+/// Counts the distinct paths from `start` to `goal`, memoising the count for
+/// every node along the way. Returns `None` if a cycle can be walked on the
+/// way to `goal`, as there'd be infinitely many paths. Cycles that can never
+/// reach `goal` don't count towards anything, so they're ignored.
+pub fn count_paths<N, C, FN, IN>(start: &N, goal: &N, mut successors: FN) -> Option<C>
+where
+    N: Eq + Hash + Clone,
+    C: Clone + Add<Output = C> + From<u8>,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = N>,
+{
+    // Map out everything reachable from `start`, stopping at `goal` as paths
+    // end there
+    let mut edges: HashMap<N, Vec<N>> = HashMap::new();
+    let mut queue = vec![start.clone()];
+    while let Some(node) = queue.pop() {
+        if edges.contains_key(&node) {
+            continue;
+        }
+        let next: Vec<N> = if node == *goal {
+            Vec::new()
+        } else {
+            successors(&node).into_iter().collect()
+        };
+        queue.extend(next.iter().filter(|n| !edges.contains_key(n)).cloned());
+        edges.insert(node, next);
+    }
+
+    // Then keep only the nodes that can get to `goal`
+    let mut reverse: HashMap<&N, Vec<&N>> = HashMap::new();
+    for (from, to) in &edges {
+        for next in to {
+            reverse.entry(next).or_default().push(from);
+        }
+    }
+    let mut useful: HashSet<&N> = HashSet::new();
+    let mut queue: Vec<&N> = edges
+        .get_key_value(goal)
+        .map(|(n, _)| n)
+        .into_iter()
+        .collect();
+    while let Some(node) = queue.pop() {
+        if useful.insert(node) {
+            queue.extend(reverse.get(node).into_iter().flatten().copied());
+        }
+    }
+    if !useful.contains(start) {
+        return Some(C::from(0));
+    }
+
+    count_acyclic(start, goal, |n: &N| {
+        edges[n]
+            .iter()
+            .filter(|next| useful.contains(next))
+            .cloned()
+            .collect::<Vec<N>>()
+    })
+}
+
+/// The memoised count behind `count_paths`, which gives up on the first
+/// cycle it finds.
+fn count_acyclic<N, C, FN, IN>(start: &N, goal: &N, mut successors: FN) -> Option<C>
+where
+    N: Eq + Hash + Clone,
+    C: Clone + Add<Output = C> + From<u8>,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = N>,
+{
+    let mut memo: HashMap<N, C> = HashMap::new();
+    let mut in_progress: HashSet<N> = HashSet::new();
+    // Each entry is a node, its successors still to count and the running total
+    let mut stack: Vec<(N, IN::IntoIter, C)> = Vec::new();
+
+    memo.insert(goal.clone(), C::from(1));
+    if let Some(count) = memo.get(start) {
+        return Some(count.clone());
+    }
+    in_progress.insert(start.clone());
+    stack.push((start.clone(), successors(start).into_iter(), C::from(0)));
+
+    while let Some((_, pending, _)) = stack.last_mut() {
+        match pending.next() {
+            Some(next) => {
+                if let Some(count) = memo.get(&next) {
+                    let count = count.clone();
+                    let top = stack.last_mut().unwrap();
+                    let acc = mem::replace(&mut top.2, C::from(0));
+                    top.2 = acc + count;
+                } else if in_progress.contains(&next) {
+                    return None;
+                } else {
+                    in_progress.insert(next.clone());
+                    let pending = successors(&next).into_iter();
+                    stack.push((next, pending, C::from(0)));
+                }
+            }
+            None => {
+                let (node, _, count) = stack.pop().unwrap();
+                in_progress.remove(&node);
+                if let Some(top) = stack.last_mut() {
+                    let acc = mem::replace(&mut top.2, C::from(0));
+                    top.2 = acc + count.clone();
+                }
+                memo.insert(node, count);
+            }
+        }
+    }
+
+    memo.remove(start)
+}
+
+/// Counts the ways to walk from the first element of `items` to the last,
+/// only ever moving forwards, where `can_step(a, b)` says whether `a` can be
+/// followed directly by `b`.
+///
+/// The predicate must be monotonic over the ordering of `items`: if `a`
+/// can't step to `b`, nothing before `a` can either. This holds for sorted
+/// input with a "no more than N apart" rule, and lets the count run in
+/// linear time for a bounded step size.
+pub fn count_chain_paths<T, C, F>(items: &[T], mut can_step: F) -> C
+where
+    C: Clone + Add<Output = C> + From<u8>,
+    F: FnMut(&T, &T) -> bool,
+{
+    if items.is_empty() {
+        return C::from(0);
+    }
+
+    let mut ways: Vec<C> = Vec::with_capacity(items.len());
+    ways.push(C::from(1));
+    for j in 1..items.len() {
+        let mut total = C::from(0);
+        for i in (0..j).rev() {
+            if !can_step(&items[i], &items[j]) {
+                break;
+            }
+            total = total + ways[i].clone();
+        }
+        ways.push(total);
+    }
+    ways.pop().unwrap()
+}
+
+/// Lazily enumerates every path from `start` to `goal` in depth-first order.
+/// The graph must be acyclic, or this will never finish.
+pub fn paths<N, FN, IN>(start: &N, goal: &N, successors: FN) -> Paths<N, FN, IN>
+where
+    N: Eq + Clone,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = N>,
+{
+    Paths {
+        start: Some(start.clone()),
+        goal: goal.clone(),
+        successors,
+        stack: Vec::new(),
+    }
+}
+
+/// Lazily enumerates the paths through `items` counted by
+/// `count_chain_paths`, with the same requirements on `can_step`.
+pub fn chain_paths<'a, T, F>(items: &'a [T], mut can_step: F) -> impl Iterator<Item = Vec<&'a T>>
+where
+    F: FnMut(&T, &T) -> bool + 'a,
+{
+    let last = items.len().wrapping_sub(1);
+    let successors = move |i: &usize| {
+        let i = *i;
+        let mut next = Vec::new();
+        for j in (i + 1)..items.len() {
+            if !can_step(&items[i], &items[j]) {
+                break;
+            }
+            next.push(j);
+        }
+        next
+    };
+    let found = if items.is_empty() {
+        None
+    } else {
+        Some(paths(&0, &last, successors))
+    };
+    found
+        .into_iter()
+        .flatten()
+        .map(move |path| path.into_iter().map(|i| &items[i]).collect())
+}
+
+pub struct Paths<N, FN, IN>
+where
+    IN: IntoIterator<Item = N>,
+{
+    start: Option<N>,
+    goal: N,
+    successors: FN,
+    stack: Vec<(N, IN::IntoIter)>,
+}
+
+impl<N, FN, IN> Iterator for Paths<N, FN, IN>
+where
+    N: Eq + Clone,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = N>,
+{
+    type Item = Vec<N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(start) = self.start.take() {
+            if start == self.goal {
+                return Some(vec![start]);
+            }
+            let pending = (self.successors)(&start).into_iter();
+            self.stack.push((start, pending));
+        }
+
+        loop {
+            let (_, pending) = self.stack.last_mut()?;
+            match pending.next() {
+                Some(next) if next == self.goal => {
+                    let mut path: Vec<N> = self.stack.iter().map(|(n, _)| n.clone()).collect();
+                    path.push(next);
+                    return Some(path);
+                }
+                Some(next) => {
+                    let pending = (self.successors)(&next).into_iter();
+                    self.stack.push((next, pending));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapters() -> Vec<u32> {
+        // The smaller example from 2020 day 10, with the outlet and device
+        let mut v = vec![0, 16, 10, 15, 5, 1, 11, 7, 19, 6, 12, 4, 22];
+        v.sort_unstable();
+        v
+    }
+
+    #[test]
+    fn chain_counts() {
+        let chain = adapters();
+        let count: u64 = count_chain_paths(&chain, |a, b| b - a <= 3);
+        assert_eq!(count, 8);
+
+        let empty: Vec<u32> = Vec::new();
+        assert_eq!(count_chain_paths::<_, u64, _>(&empty, |_, _| true), 0);
+        assert_eq!(count_chain_paths::<_, u64, _>(&[5], |_, _| true), 1);
+    }
+
+    #[test]
+    fn chain_counts_large() {
+        // Every element can reach every later one, giving 2^(n-2) paths
+        let chain: Vec<u32> = (0..100).collect();
+        let count: u128 = count_chain_paths(&chain, |_, _| true);
+        assert_eq!(count, 1u128 << 98);
+    }
+
+    #[test]
+    fn chain_enumeration() {
+        let chain = adapters();
+        let all: Vec<Vec<&u32>> = chain_paths(&chain, |a, b| b - a <= 3).collect();
+        assert_eq!(all.len(), 8);
+        assert!(all
+            .iter()
+            .all(|p| p.first() == Some(&&0) && p.last() == Some(&&22)));
+        assert_eq!(all[0].len(), 13);
+
+        let empty: Vec<u32> = Vec::new();
+        assert_eq!(chain_paths(&empty, |_, _| true).count(), 0);
+    }
+
+    #[test]
+    fn graph_counts() {
+        let edges: HashMap<char, Vec<char>> = vec![
+            ('a', vec!['b', 'c']),
+            ('b', vec!['d']),
+            ('c', vec!['d', 'e']),
+            ('d', vec!['e']),
+        ]
+        .into_iter()
+        .collect();
+        let successors = |n: &char| edges.get(n).cloned().unwrap_or_default();
+
+        assert_eq!(count_paths::<_, u64, _, _>(&'a', &'e', successors), Some(3));
+        assert_eq!(count_paths::<_, u64, _, _>(&'e', &'a', successors), Some(0));
+        assert_eq!(count_paths::<_, u64, _, _>(&'a', &'a', successors), Some(1));
+
+        let mut all: Vec<String> = paths(&'a', &'e', successors)
+            .map(|p| p.into_iter().collect())
+            .collect();
+        all.sort();
+        assert_eq!(all, vec!["abde", "acde", "ace"]);
+    }
+
+    #[test]
+    fn graph_cycle() {
+        let successors = |n: &u32| match n {
+            0 => vec![1],
+            1 => vec![2, 3],
+            2 => vec![0],
+            _ => vec![],
+        };
+        assert_eq!(count_paths::<_, u64, _, _>(&0, &3, successors), None);
+    }
+
+    #[test]
+    fn graph_dead_end_cycle() {
+        // 2 and 4 loop, and 2 can get to the goal at 3
+        let successors = |n: &u32| match n {
+            0 => vec![1, 2],
+            1 => vec![3],
+            2 => vec![4, 3],
+            4 => vec![2, 5],
+            5 => vec![4],
+            _ => vec![],
+        };
+        assert_eq!(count_paths::<_, u64, _, _>(&0, &3, successors), None);
+
+        // 4 and 5 loop, but neither can get to the goal
+        let successors = |n: &u32| match n {
+            0 => vec![1, 2],
+            1 => vec![3],
+            2 => vec![4, 3],
+            4 => vec![5],
+            5 => vec![4],
+            _ => vec![],
+        };
+        assert_eq!(count_paths::<_, u64, _, _>(&0, &3, successors), Some(2));
+        assert_eq!(count_paths::<_, u64, _, _>(&4, &3, successors), Some(0));
+    }
+}
@@ -0,0 +1,409 @@
+use crate::grid::Grid;
+use petgraph::visit::{EdgeRef, IntoEdges, IntoNeighbors};
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+/// Walks back through a map of node -> parent to build the path that ends at
+/// `goal`. The returned path runs from the start node to `goal`.
+pub fn reconstruct_path<N>(parents: &HashMap<N, N>, goal: &N) -> Vec<N>
+where
+    N: Eq + Hash + Clone,
+{
+    let mut path = vec![goal.clone()];
+    let mut current = goal;
+    while let Some(parent) = parents.get(current) {
+        path.push(parent.clone());
+        current = parent;
+    }
+    path.reverse();
+    path
+}
+
+/// Every node reachable from `start`, not including `start` itself unless
+/// there's a cycle back to it.
+pub fn reachable<N, FN, IN>(start: &N, mut successors: FN) -> HashSet<N>
+where
+    N: Eq + Hash + Clone,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = N>,
+{
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(start.clone());
+    while let Some(node) = queue.pop_front() {
+        for next in successors(&node) {
+            if !seen.contains(&next) {
+                seen.insert(next.clone());
+                queue.push_back(next);
+            }
+        }
+    }
+    seen
+}
+
+/// Breadth-first search from `start` returning the shortest path (in number
+/// of steps) to the first node satisfying `success`.
+pub fn bfs<N, FN, IN, FS>(start: &N, mut successors: FN, mut success: FS) -> Option<Vec<N>>
+where
+    N: Eq + Hash + Clone,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = N>,
+    FS: FnMut(&N) -> bool,
+{
+    if success(start) {
+        return Some(vec![start.clone()]);
+    }
+
+    let mut parents = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(start.clone());
+    while let Some(node) = queue.pop_front() {
+        for next in successors(&node) {
+            if next == *start || parents.contains_key(&next) {
+                continue;
+            }
+            parents.insert(next.clone(), node.clone());
+            if success(&next) {
+                return Some(reconstruct_path(&parents, &next));
+            }
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+struct Frontier<N, C> {
+    node: N,
+    cost: C,
+    estimate: C,
+}
+
+impl<N, C: Ord> PartialEq for Frontier<N, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate && self.cost == other.cost
+    }
+}
+
+impl<N, C: Ord> Eq for Frontier<N, C> {}
+
+impl<N, C: Ord> PartialOrd for Frontier<N, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N, C: Ord> Ord for Frontier<N, C> {
+    // Reversed so that BinaryHeap pops the cheapest estimate first, breaking
+    // ties in favour of whichever has come further.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .cmp(&self.estimate)
+            .then(self.cost.cmp(&other.cost))
+    }
+}
+
+/// A* search from `start` to the first node satisfying `success`. The
+/// heuristic must never overestimate the remaining cost for the returned
+/// path to be optimal. Returns the path and its total cost.
+pub fn astar<N, C, FN, IN, FH, FS>(
+    start: &N,
+    mut successors: FN,
+    mut heuristic: FH,
+    mut success: FS,
+) -> Option<(Vec<N>, C)>
+where
+    N: Eq + Hash + Clone,
+    C: Ord + Copy + Default + Add<Output = C>,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = (N, C)>,
+    FH: FnMut(&N) -> C,
+    FS: FnMut(&N) -> bool,
+{
+    let mut parents: HashMap<N, N> = HashMap::new();
+    let mut best: HashMap<N, C> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+
+    best.insert(start.clone(), C::default());
+    heap.push(Frontier {
+        node: start.clone(),
+        cost: C::default(),
+        estimate: heuristic(start),
+    });
+
+    while let Some(Frontier { node, cost, .. }) = heap.pop() {
+        if success(&node) {
+            return Some((reconstruct_path(&parents, &node), cost));
+        }
+        // Skip stale entries that have since been reached more cheaply
+        if best.get(&node).is_some_and(|b| cost > *b) {
+            continue;
+        }
+
+        for (next, step_cost) in successors(&node) {
+            let next_cost = cost + step_cost;
+            match best.entry(next.clone()) {
+                Entry::Occupied(mut o) => {
+                    if next_cost >= *o.get() {
+                        continue;
+                    }
+                    o.insert(next_cost);
+                }
+                Entry::Vacant(v) => {
+                    v.insert(next_cost);
+                }
+            }
+            if next != *start {
+                parents.insert(next.clone(), node.clone());
+            }
+            let estimate = next_cost + heuristic(&next);
+            heap.push(Frontier {
+                node: next,
+                cost: next_cost,
+                estimate,
+            });
+        }
+    }
+    None
+}
+
+/// Dijkstra's algorithm from `start` to the first node satisfying `success`.
+/// Returns the path and its total cost.
+pub fn dijkstra<N, C, FN, IN, FS>(start: &N, successors: FN, success: FS) -> Option<(Vec<N>, C)>
+where
+    N: Eq + Hash + Clone,
+    C: Ord + Copy + Default + Add<Output = C>,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = (N, C)>,
+    FS: FnMut(&N) -> bool,
+{
+    astar(start, successors, |_| C::default(), success)
+}
+
+/// Runs Dijkstra's algorithm over everything reachable from `start`,
+/// returning the cheapest cost to each node along with the parent map for
+/// use with `reconstruct_path`.
+pub fn dijkstra_all<N, C, FN, IN>(start: &N, mut successors: FN) -> (HashMap<N, C>, HashMap<N, N>)
+where
+    N: Eq + Hash + Clone,
+    C: Ord + Copy + Default + Add<Output = C>,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = (N, C)>,
+{
+    let mut parents: HashMap<N, N> = HashMap::new();
+    let mut best: HashMap<N, C> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+
+    best.insert(start.clone(), C::default());
+    heap.push(Frontier {
+        node: start.clone(),
+        cost: C::default(),
+        estimate: C::default(),
+    });
+
+    while let Some(Frontier { node, cost, .. }) = heap.pop() {
+        if best.get(&node).is_some_and(|b| cost > *b) {
+            continue;
+        }
+        for (next, step_cost) in successors(&node) {
+            let next_cost = cost + step_cost;
+            if best.get(&next).is_some_and(|b| next_cost >= *b) {
+                continue;
+            }
+            best.insert(next.clone(), next_cost);
+            if next != *start {
+                parents.insert(next.clone(), node.clone());
+            }
+            heap.push(Frontier {
+                node: next,
+                cost: next_cost,
+                estimate: next_cost,
+            });
+        }
+    }
+    (best, parents)
+}
+
+type GridCoordinate = (usize, usize);
+
+fn orthogonal_neighbours<G>(grid: &G, coord: &GridCoordinate) -> Vec<GridCoordinate>
+where
+    G: Grid<Coordinate = (usize, usize)>,
+{
+    let (x, y) = *coord;
+    let mut around = Vec::with_capacity(4);
+    if x > 0 {
+        around.push((x - 1, y));
+    }
+    if y > 0 {
+        around.push((x, y - 1));
+    }
+    if x + 1 < grid.width() {
+        around.push((x + 1, y));
+    }
+    if y + 1 < grid.height() {
+        around.push((x, y + 1));
+    }
+    around
+}
+
+/// Successor function over a grid moving up, down, left and right onto any
+/// cell the `passable` predicate accepts.
+pub fn grid_successors<'a, G, P>(
+    grid: &'a G,
+    passable: P,
+) -> impl Fn(&(usize, usize)) -> Vec<(usize, usize)> + 'a
+where
+    G: Grid<Coordinate = (usize, usize)>,
+    P: Fn(&G::Value) -> bool + 'a,
+{
+    move |coord| {
+        orthogonal_neighbours(grid, coord)
+            .into_iter()
+            .filter(|next| grid.at(next).is_some_and(&passable))
+            .collect()
+    }
+}
+
+/// Weighted successor function over a grid moving up, down, left and right.
+/// `cost` gives the price of entering a cell, or `None` if it's impassable.
+pub fn grid_weighted_successors<'a, G, C, F>(
+    grid: &'a G,
+    cost: F,
+) -> impl Fn(&GridCoordinate) -> Vec<(GridCoordinate, C)> + 'a
+where
+    G: Grid<Coordinate = (usize, usize)>,
+    F: Fn(&G::Value) -> Option<C> + 'a,
+{
+    move |coord| {
+        orthogonal_neighbours(grid, coord)
+            .into_iter()
+            .filter_map(|next| grid.at(&next).and_then(&cost).map(|c| (next, c)))
+            .collect()
+    }
+}
+
+/// Successor function following the outgoing edges of a petgraph graph.
+pub fn graph_successors<G>(graph: G) -> impl Fn(&G::NodeId) -> Vec<G::NodeId>
+where
+    G: IntoNeighbors + Copy,
+{
+    move |node| graph.neighbors(*node).collect()
+}
+
+/// Weighted successor function following the outgoing edges of a petgraph
+/// graph, using each edge's weight as its cost.
+pub fn graph_weighted_successors<G>(
+    graph: G,
+) -> impl Fn(&G::NodeId) -> Vec<(G::NodeId, G::EdgeWeight)>
+where
+    G: IntoEdges + Copy,
+    G::EdgeWeight: Copy,
+{
+    move |node| {
+        graph
+            .edges(*node)
+            .map(|e| (e.target(), *e.weight()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::VecGrid;
+    use petgraph::graph::Graph;
+
+    fn maze() -> VecGrid<char> {
+        VecGrid::from_rows(
+            vec!["S.#....", ".##.##.", "...#...", ".#...#E"]
+                .into_iter()
+                .map(|row| row.chars()),
+        )
+    }
+
+    #[test]
+    fn bfs_grid() {
+        let grid = maze();
+        let successors = grid_successors(&grid, |c| *c != '#');
+        let path = bfs(&(0, 0), successors, |p| *p == (6, 3)).unwrap();
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(6, 3)));
+        assert_eq!(path.len(), 12);
+
+        let walled = grid_successors(&grid, |c| *c == '.' || *c == 'S');
+        assert_eq!(bfs(&(0, 0), walled, |p| *p == (6, 3)), None);
+    }
+
+    #[test]
+    fn bfs_start_is_goal() {
+        let path = bfs(&1, |n| vec![n + 1], |n| *n == 1);
+        assert_eq!(path, Some(vec![1]));
+    }
+
+    #[test]
+    fn reachable_nodes() {
+        let found = reachable(&0, |n| if *n < 5 { vec![n + 1, n + 2] } else { vec![] });
+        let mut found: Vec<_> = found.into_iter().collect();
+        found.sort_unstable();
+        assert_eq!(found, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn dijkstra_weighted() {
+        // 0 -> 1 is expensive directly but cheap via 2
+        let edges: HashMap<u32, Vec<(u32, u32)>> = vec![
+            (0, vec![(1, 10), (2, 1)]),
+            (2, vec![(1, 2), (3, 20)]),
+            (1, vec![(3, 1)]),
+        ]
+        .into_iter()
+        .collect();
+        let successors = |n: &u32| edges.get(n).cloned().unwrap_or_default();
+
+        let (path, cost) = dijkstra(&0, successors, |n| *n == 3).unwrap();
+        assert_eq!(path, vec![0, 2, 1, 3]);
+        assert_eq!(cost, 4);
+
+        let (costs, parents) = dijkstra_all(&0, successors);
+        assert_eq!(costs.get(&1), Some(&3));
+        assert_eq!(costs.get(&3), Some(&4));
+        assert_eq!(reconstruct_path(&parents, &3), vec![0, 2, 1, 3]);
+        assert_eq!(dijkstra(&3, successors, |n| *n == 0), None);
+    }
+
+    #[test]
+    fn astar_grid() {
+        let grid = maze();
+        let successors = grid_weighted_successors(&grid, |c| match c {
+            '#' => None,
+            _ => Some(1usize),
+        });
+        let goal = (6, 3);
+        let manhattan = |p: &(usize, usize)| p.0.abs_diff(goal.0) + p.1.abs_diff(goal.1);
+        let (path, cost) = astar(&(0, 0), successors, manhattan, |p| *p == goal).unwrap();
+        assert_eq!(cost, 11);
+        assert_eq!(path.len(), 12);
+    }
+
+    #[test]
+    fn petgraph_adapters() {
+        let mut graph = Graph::<&str, u32>::new();
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        graph.add_edge(a, b, 5);
+        graph.add_edge(b, c, 5);
+        graph.add_edge(a, c, 20);
+
+        let path = bfs(&a, graph_successors(&graph), |n| *n == c).unwrap();
+        assert_eq!(path, vec![a, c]);
+
+        let (path, cost) = dijkstra(&a, graph_weighted_successors(&graph), |n| *n == c).unwrap();
+        assert_eq!(path, vec![a, b, c]);
+        assert_eq!(cost, 10);
+    }
+}
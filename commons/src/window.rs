@@ -0,0 +1,216 @@
+use crate::collections::RingBuffer;
+use crate::ksum::Summable;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::{Add, Range};
+
+/// A fixed-size window over a stream of values which keeps track of every
+/// sum of two (distinct position) values inside it. Pushing is linear in the
+/// window size, but asking whether a sum is present is a single lookup.
+#[derive(Debug, Clone)]
+pub struct PairSumWindow<T> {
+    values: RingBuffer<T>,
+    sums: HashMap<T, usize>,
+}
+
+impl<T> PairSumWindow<T>
+where
+    T: Copy + Eq + Hash + Add<Output = T>,
+{
+    pub fn new(capacity: usize) -> Self {
+        PairSumWindow {
+            values: RingBuffer::new(capacity),
+            sums: HashMap::new(),
+        }
+    }
+
+    /// Adds a value to the window, returning the oldest value if it had to
+    /// be pushed out to make room.
+    pub fn push(&mut self, value: T) -> Option<T> {
+        if self.values.capacity() == 0 {
+            return Some(value);
+        }
+
+        let evicted = self.values.push(value);
+        // Everything but the value just pushed
+        let older = self.values.len() - 1;
+        if let Some(oldest) = evicted {
+            for v in self.values.iter().take(older) {
+                let sum = oldest + *v;
+                let count = self.sums.get_mut(&sum).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    self.sums.remove(&sum);
+                }
+            }
+        }
+
+        for v in self.values.iter().take(older) {
+            *self.sums.entry(value + *v).or_insert(0) += 1;
+        }
+        evicted
+    }
+
+    pub fn contains_pair_sum(&self, target: &T) -> bool {
+        self.sums.contains_key(target)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.values.is_full()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.values.iter()
+    }
+}
+
+/// Finds a run of at least `min_len` consecutive values summing to `target`
+/// using prefix sums, so negative values are fine. Returns the longest such
+/// run ending at the earliest possible position.
+pub fn contiguous_sum<T>(values: &[T], target: T, min_len: usize) -> Option<Range<usize>>
+where
+    T: Summable + Default,
+{
+    let min_len = min_len.max(1);
+    // prefixes[i] is the sum of values[..i]
+    let mut prefixes = Vec::with_capacity(values.len() + 1);
+    prefixes.push(T::default());
+    for v in values {
+        let last = *prefixes.last().unwrap();
+        prefixes.push(last + *v);
+    }
+
+    // Earliest position of each prefix sum that's far enough back to start a
+    // long enough run
+    let mut earliest: HashMap<T, usize> = HashMap::new();
+    for end in min_len..prefixes.len() {
+        let start = end - min_len;
+        earliest.entry(prefixes[start]).or_insert(start);
+        if let Some(&start) = prefixes[end]
+            .checked_diff(target)
+            .and_then(|p| earliest.get(&p))
+        {
+            return Some(start..end);
+        }
+    }
+    None
+}
+
+/// As `contiguous_sum`, but for inputs with no negative values, where the
+/// run can be found with a pair of pointers and no extra memory.
+pub fn contiguous_sum_non_negative<T>(
+    values: &[T],
+    target: T,
+    min_len: usize,
+) -> Option<Range<usize>>
+where
+    T: Summable + Default,
+{
+    let min_len = min_len.max(1);
+    let mut start = 0;
+    let mut sum = T::default();
+    for (end, v) in values.iter().enumerate() {
+        sum = sum + *v;
+        while sum > target && start <= end {
+            // Only ever subtracts values already added, so can't underflow
+            sum = sum.checked_diff(values[start]).unwrap();
+            start += 1;
+        }
+        if sum == target && end + 1 - start >= min_len {
+            return Some(start..end + 1);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The XMAS example from 2020 day 9
+    const XMAS: [u64; 20] = [
+        35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309, 576,
+    ];
+
+    #[test]
+    fn pair_sum_window() {
+        let mut window = PairSumWindow::new(5);
+        for v in &XMAS[..5] {
+            assert_eq!(window.push(*v), None);
+        }
+        assert!(window.is_full());
+
+        let mut invalid = None;
+        for v in &XMAS[5..] {
+            if !window.contains_pair_sum(v) {
+                invalid = Some(*v);
+                break;
+            }
+            window.push(*v);
+        }
+        assert_eq!(invalid, Some(127));
+        assert_eq!(window.len(), 5);
+        assert_eq!(
+            window.iter().copied().collect::<Vec<_>>(),
+            vec![95, 102, 117, 150, 182]
+        );
+    }
+
+    #[test]
+    fn pair_sum_window_duplicates() {
+        let mut window = PairSumWindow::new(3);
+        window.push(2);
+        // A value can't pair with itself
+        assert!(!window.contains_pair_sum(&4));
+        window.push(2);
+        assert!(window.contains_pair_sum(&4));
+        window.push(3);
+        assert_eq!(window.push(1), Some(2));
+        assert!(window.contains_pair_sum(&4));
+        assert_eq!(window.push(7), Some(2));
+        assert!(!window.contains_pair_sum(&5));
+        assert!(window.contains_pair_sum(&4));
+        assert!(window.contains_pair_sum(&10));
+    }
+
+    #[test]
+    fn contiguous() {
+        assert_eq!(contiguous_sum(&XMAS, 127, 2), Some(2..6));
+        assert_eq!(contiguous_sum_non_negative(&XMAS, 127, 2), Some(2..6));
+        // The run is found long before reaching 127 itself
+        assert_eq!(contiguous_sum_non_negative(&XMAS, 127, 1), Some(2..6));
+        assert_eq!(contiguous_sum(&XMAS[10..], 127, 1), Some(4..5));
+        assert_eq!(contiguous_sum(&XMAS, 1, 1), None);
+        assert_eq!(contiguous_sum_non_negative(&XMAS, 1, 1), None);
+
+        let empty: [u32; 0] = [];
+        assert_eq!(contiguous_sum(&empty, 0, 1), None);
+        assert_eq!(contiguous_sum_non_negative(&empty, 0, 1), None);
+    }
+
+    #[test]
+    fn contiguous_negative() {
+        let values = [4, -3, 5, -2, 1, 7];
+        assert_eq!(contiguous_sum(&values, 6, 1), Some(0..3));
+        assert_eq!(contiguous_sum(&values, -1, 2), Some(3..5));
+        assert_eq!(contiguous_sum(&values, 3, 3), None);
+    }
+
+    #[test]
+    fn contiguous_zeros() {
+        let values = [5u32, 0, 0, 3];
+        assert_eq!(contiguous_sum_non_negative(&values, 5, 1), Some(0..1));
+        assert_eq!(contiguous_sum_non_negative(&values, 5, 3), Some(0..3));
+        assert_eq!(contiguous_sum(&values, 5, 3), Some(0..3));
+        assert_eq!(contiguous_sum_non_negative(&values, 3, 2), Some(1..4));
+        assert_eq!(contiguous_sum(&values, 3, 2), Some(1..4));
+    }
+}
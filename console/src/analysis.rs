@@ -0,0 +1,125 @@
+use crate::program::Program;
+use std::collections::VecDeque;
+
+// The address after `address`, if it's in the program or just past the end.
+// Custom instructions could go anywhere, so they're treated as never halting.
+fn successor(program: &Program, address: usize) -> Option<usize> {
+    program[address]
+        .next_address(address)
+        .filter(|&next| next >= 0 && next as usize <= program.len())
+        .map(|next| next as usize)
+}
+
+/// For each address, whether running the program from there halts. Works
+/// backwards from the end of the program, so it's linear in its length.
+pub fn halting_addresses(program: &Program) -> Vec<bool> {
+    // Index `program.len()` stands in for the halt itself
+    let mut predecessors = vec![Vec::new(); program.len() + 1];
+    for address in 0..program.len() {
+        if let Some(next) = successor(program, address) {
+            predecessors[next].push(address);
+        }
+    }
+
+    let mut halts = vec![false; program.len() + 1];
+    halts[program.len()] = true;
+    let mut queue: VecDeque<usize> = vec![program.len()].into();
+    while let Some(address) = queue.pop_front() {
+        for &prev in &predecessors[address] {
+            if !halts[prev] {
+                halts[prev] = true;
+                queue.push_back(prev);
+            }
+        }
+    }
+    halts.truncate(program.len());
+    halts
+}
+
+/// The addresses run from the start, in order, up to the first repeat or the
+/// program stopping.
+pub fn execution_path(program: &Program) -> Vec<usize> {
+    let mut seen = vec![false; program.len()];
+    let mut path = Vec::new();
+    let mut address = 0;
+    while address < program.len() && !seen[address] {
+        seen[address] = true;
+        path.push(address);
+        address = match successor(program, address) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    path
+}
+
+/// Finds the single `jmp` or `nop` to flip to make the program halt, without
+/// running any of the candidate programs.
+///
+/// Flipping anything the program never reaches can't change what it does,
+/// so only the path it actually takes is tried. A flip on that path works
+/// exactly when it sends execution somewhere that halts unchanged. Returns
+/// `None` if the program halts already, or if no one flip fixes it.
+pub fn find_flip(program: &Program) -> Option<usize> {
+    let path = execution_path(program);
+    let halts = halting_addresses(program);
+    if path.first().is_none_or(|&start| halts[start]) {
+        return None;
+    }
+
+    path.into_iter().find(|&address| {
+        program[address]
+            .flipped()
+            .and_then(|ins| ins.next_address(address))
+            .filter(|&next| next >= 0 && next as usize <= program.len())
+            .is_some_and(|next| next as usize == program.len() || halts[next as usize])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::{Machine, Termination};
+    use crate::program::tests::EXAMPLE;
+
+    #[test]
+    fn example() {
+        let program = Program::assemble(EXAMPLE).unwrap();
+        assert_eq!(execution_path(&program), vec![0, 1, 2, 6, 7, 3, 4]);
+        assert_eq!(
+            halting_addresses(&program),
+            vec![false, false, false, false, false, false, false, false, true]
+        );
+        assert_eq!(find_flip(&program), Some(7));
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let programs = [
+            EXAMPLE,
+            "nop +2\njmp +0\nacc +1\njmp -1",
+            "jmp +2\nacc +1\nnop -2\nacc +3\njmp -4",
+            "acc +1\njmp +5\njmp -2\nnop +0\njmp -3",
+            "acc +1\nnop +1\njmp -2",
+        ];
+        for source in &programs {
+            let program = Program::assemble(source).unwrap();
+            let brute_force = (0..program.len()).find(|&i| {
+                program
+                    .with_flipped(i)
+                    .is_some_and(|p| Machine::new(&p).run() == Termination::Halted)
+            });
+            assert_eq!(find_flip(&program), brute_force, "{}", source);
+        }
+    }
+
+    #[test]
+    fn nothing_to_fix() {
+        let program = Program::assemble("acc +1\nnop +0").unwrap();
+        assert_eq!(find_flip(&program), None);
+        assert_eq!(find_flip(&Program::default()), None);
+        // Breaking the loop just runs into a bad jump
+        let program = Program::assemble("acc +1\njmp -1\njmp -3").unwrap();
+        assert_eq!(find_flip(&program), None);
+    }
+}
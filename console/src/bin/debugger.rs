@@ -0,0 +1,52 @@
+use console::debugger::{Command, Debugger};
+use console::Program;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+fn prompt() {
+    print!("(console) ");
+    io::stdout().flush().unwrap();
+}
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: debugger PROGRAM");
+            process::exit(1);
+        }
+    };
+    let source = fs::read_to_string(&path).expect("Could not open program file");
+    let program = match Program::assemble(&source) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    let mut debugger = Debugger::new(&program);
+    let mut last_command = None;
+    prompt();
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap();
+        // An empty line repeats the last command, as in gdb
+        let command = if line.trim().is_empty() {
+            last_command.ok_or_else(|| "Empty command".to_string())
+        } else {
+            line.parse::<Command>().map_err(|e| e.to_string())
+        };
+        match command {
+            Ok(Command::Quit) => return,
+            Ok(command) => {
+                print!("{}", debugger.execute(command));
+                last_command = Some(command);
+            }
+            Err(e) => println!("{}", e),
+        }
+        prompt();
+    }
+    println!();
+}
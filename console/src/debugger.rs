@@ -0,0 +1,265 @@
+use crate::machine::{Machine, Stop};
+use crate::program::Program;
+use std::fmt::Write;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    Acc,
+    Pc,
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Step(usize),
+    Continue,
+    Print(Register),
+    Break(usize),
+    Clear(usize),
+    Trace,
+    List,
+    Reset,
+    Help,
+    Quit,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CommandError {
+    #[error("Empty command")]
+    Empty,
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+    #[error("Argument missing for command: {0}")]
+    MissingArgument(String),
+    #[error("Argument invalid for command: {0}")]
+    InvalidArgument(String),
+}
+
+impl FromStr for Command {
+    type Err = CommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut it = s.split_whitespace();
+        let name = it.next().ok_or(CommandError::Empty)?;
+        let arg = it.next();
+        let address = || match arg {
+            Some(a) => a
+                .parse()
+                .map_err(|_| CommandError::InvalidArgument(s.to_string())),
+            None => Err(CommandError::MissingArgument(s.to_string())),
+        };
+
+        let command = match name {
+            "s" | "step" => match arg {
+                Some(_) => Command::Step(address()?),
+                None => Command::Step(1),
+            },
+            "c" | "continue" => Command::Continue,
+            "p" | "print" => Command::Print(match arg {
+                Some("acc") => Register::Acc,
+                Some("pc") => Register::Pc,
+                None => Register::All,
+                Some(_) => return Err(CommandError::InvalidArgument(s.to_string())),
+            }),
+            "b" | "break" => Command::Break(address()?),
+            "clear" => Command::Clear(address()?),
+            "t" | "trace" => Command::Trace,
+            "l" | "list" => Command::List,
+            "reset" => Command::Reset,
+            "h" | "help" => Command::Help,
+            "q" | "quit" => Command::Quit,
+            _ => return Err(CommandError::UnknownCommand(s.to_string())),
+        };
+        Ok(command)
+    }
+}
+
+const HELP: &str = "step [N]     run one (or N) instructions
+continue     run until a breakpoint or the program stops
+print [REG]  show acc, pc or both
+break N      stop before running address N
+clear N      remove the breakpoint at address N
+trace        show every instruction run so far
+list         show the program
+reset        go back to the start of the program
+quit         leave the debugger";
+
+/// Drives a machine from text commands, always tracing. Each command gives
+/// back the text to show for it.
+pub struct Debugger<'a> {
+    machine: Machine<'a>,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(program: &'a Program) -> Debugger<'a> {
+        let mut machine = Machine::new(program);
+        machine.set_tracing(true);
+        Debugger { machine }
+    }
+
+    pub fn machine(&self) -> &Machine<'a> {
+        &self.machine
+    }
+
+    pub fn execute(&mut self, command: Command) -> String {
+        let mut out = String::new();
+        match command {
+            Command::Step(n) => {
+                for _ in 0..n {
+                    if let Some(termination) = self.machine.step() {
+                        writeln!(out, "Stopped: {}", termination).unwrap();
+                        break;
+                    }
+                }
+                out += &self.location();
+            }
+            Command::Continue => {
+                match self.machine.resume() {
+                    Stop::Breakpoint(address) => {
+                        writeln!(out, "Breakpoint at {}", address).unwrap()
+                    }
+                    Stop::Terminated(termination) => {
+                        writeln!(out, "Stopped: {}", termination).unwrap()
+                    }
+                }
+                out += &self.location();
+            }
+            Command::Print(register) => {
+                let registers = self.machine.registers();
+                match register {
+                    Register::Acc => writeln!(out, "acc = {}", registers.acc),
+                    Register::Pc => writeln!(out, "pc = {}", registers.pc),
+                    Register::All => {
+                        writeln!(out, "pc = {}, acc = {}", registers.pc, registers.acc)
+                    }
+                }
+                .unwrap();
+            }
+            Command::Break(address) => {
+                if address < self.machine.program().len() {
+                    self.machine.set_breakpoint(address);
+                    writeln!(out, "Breakpoint set at {}", address).unwrap();
+                } else {
+                    writeln!(out, "No instruction at {}", address).unwrap();
+                }
+            }
+            Command::Clear(address) => {
+                if self.machine.clear_breakpoint(address) {
+                    writeln!(out, "Breakpoint cleared at {}", address).unwrap();
+                } else {
+                    writeln!(out, "No breakpoint at {}", address).unwrap();
+                }
+            }
+            Command::Trace => {
+                for entry in self.machine.trace() {
+                    writeln!(
+                        out,
+                        "{:>5}  {:<8}  acc = {}",
+                        entry.pc, entry.instruction, entry.acc
+                    )
+                    .unwrap();
+                }
+            }
+            Command::List => {
+                let pc = self.machine.registers().pc;
+                let breakpoints: Vec<usize> = self.machine.breakpoints().collect();
+                for (address, ins) in self.machine.program().instructions().iter().enumerate() {
+                    let current = if address == pc { "=>" } else { "  " };
+                    let marker = if breakpoints.contains(&address) {
+                        '*'
+                    } else {
+                        ' '
+                    };
+                    writeln!(out, "{}{}{:>5}  {}", current, marker, address, ins).unwrap();
+                }
+            }
+            Command::Reset => {
+                self.machine.reset();
+                out += &self.location();
+            }
+            Command::Help => {
+                out += HELP;
+                out.push('\n');
+            }
+            Command::Quit => {}
+        }
+        out
+    }
+
+    // Where the machine is and what it'll run next
+    fn location(&self) -> String {
+        let registers = self.machine.registers();
+        match self.machine.program().get(registers.pc) {
+            Some(ins) if self.machine.termination().is_none() => format!(
+                "pc = {}, acc = {}, next: {}\n",
+                registers.pc, registers.acc, ins
+            ),
+            _ => format!("pc = {}, acc = {}\n", registers.pc, registers.acc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::tests::EXAMPLE;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!("step".parse(), Ok(Command::Step(1)));
+        assert_eq!("s 3".parse(), Ok(Command::Step(3)));
+        assert_eq!("continue".parse(), Ok(Command::Continue));
+        assert_eq!("print acc".parse(), Ok(Command::Print(Register::Acc)));
+        assert_eq!("p".parse(), Ok(Command::Print(Register::All)));
+        assert_eq!("break 4".parse(), Ok(Command::Break(4)));
+        assert_eq!("".parse::<Command>(), Err(CommandError::Empty));
+        assert_eq!(
+            "break".parse::<Command>(),
+            Err(CommandError::MissingArgument("break".to_string()))
+        );
+        assert_eq!(
+            "print sp".parse::<Command>(),
+            Err(CommandError::InvalidArgument("print sp".to_string()))
+        );
+        assert_eq!(
+            "jump 3".parse::<Command>(),
+            Err(CommandError::UnknownCommand("jump 3".to_string()))
+        );
+    }
+
+    #[test]
+    fn session() {
+        let program = Program::assemble(EXAMPLE).unwrap();
+        let mut debugger = Debugger::new(&program);
+        let mut run = |command: &str| debugger.execute(command.parse().unwrap());
+
+        assert_eq!(run("step"), "pc = 1, acc = 0, next: acc +1\n");
+        assert_eq!(run("break 7"), "Breakpoint set at 7\n");
+        assert_eq!(run("break 20"), "No instruction at 20\n");
+        assert_eq!(
+            run("continue"),
+            "Breakpoint at 7\npc = 7, acc = 2, next: jmp -4\n"
+        );
+        assert_eq!(run("print acc"), "acc = 2\n");
+        assert_eq!(run("continue"), "Stopped: infinite loop\npc = 1, acc = 5\n");
+        assert_eq!(run("trace").lines().count(), 7);
+        assert_eq!(run("clear 7"), "Breakpoint cleared at 7\n");
+        assert_eq!(run("reset"), "pc = 0, acc = 0, next: nop +0\n");
+        assert_eq!(run("step 2"), "pc = 2, acc = 1, next: jmp +4\n");
+        run("break 1");
+        assert!(run("list").starts_with("       0  nop +0\n  *    1  acc +1\n=>     2  jmp +4\n"));
+    }
+
+    #[test]
+    fn runs_off_the_end() {
+        let program = Program::assemble("acc +2\nnop +0").unwrap();
+        let mut debugger = Debugger::new(&program);
+        assert_eq!(
+            debugger.execute(Command::Step(5)),
+            "Stopped: halted\npc = 2, acc = 2\n"
+        );
+        assert_eq!(debugger.machine().trace().len(), 2);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::opcode::{Custom, InstructionSet};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Accumulate(i32),
+    Jump(i32),
+    Nop(i32),
+    /// Anything registered with an `InstructionSet`.
+    Custom(Custom),
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("Empty instruction")]
+    Empty,
+    #[error("Unknown instruction: {0}")]
+    UnknownInstruction(String),
+    #[error("Argument missing for instruction: {0}")]
+    MissingArgument(String),
+    #[error("Argument invalid for instruction: {0}")]
+    InvalidArgument(String),
+    #[error("Too many arguments for instruction: {0}")]
+    ExtraArgument(String),
+}
+
+impl Instruction {
+    pub fn mnemonic(&self) -> &str {
+        match self {
+            Instruction::Accumulate(_) => "acc",
+            Instruction::Jump(_) => "jmp",
+            Instruction::Nop(_) => "nop",
+            Instruction::Custom(custom) => custom.opcode.mnemonic(),
+        }
+    }
+
+    /// The single argument of a built-in instruction.
+    pub fn argument(&self) -> Option<i32> {
+        match self {
+            Instruction::Accumulate(i) | Instruction::Jump(i) | Instruction::Nop(i) => Some(*i),
+            Instruction::Custom(_) => None,
+        }
+    }
+
+    /// The address run after this instruction when it's at `address`, which
+    /// might be outside the program. Where a custom instruction goes depends
+    /// on what it does at the time, so that's `None`.
+    pub fn next_address(&self, address: usize) -> Option<isize> {
+        match self {
+            Instruction::Jump(i) => Some(address as isize + *i as isize),
+            Instruction::Custom(_) => None,
+            _ => Some(address as isize + 1),
+        }
+    }
+
+    /// Swaps a `jmp` for a `nop` or the other way round, keeping the
+    /// argument. There's nothing to swap an `acc` for.
+    pub fn flipped(&self) -> Option<Instruction> {
+        match self {
+            Instruction::Jump(i) => Some(Instruction::Nop(*i)),
+            Instruction::Nop(i) => Some(Instruction::Jump(*i)),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ParseError;
+
+    /// Parses one of the built-in instructions. Use an `InstructionSet` to
+    /// parse anything else.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        InstructionSet::new().parse(s)
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Custom(custom) => write!(f, "{}", custom),
+            _ => write!(f, "{} {:+}", self.mnemonic(), self.argument().unwrap()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("acc +3".parse(), Ok(Instruction::Accumulate(3)));
+        assert_eq!("jmp -4".parse(), Ok(Instruction::Jump(-4)));
+        assert_eq!("nop +0".parse(), Ok(Instruction::Nop(0)));
+        assert_eq!("".parse::<Instruction>(), Err(ParseError::Empty));
+        assert_eq!(
+            "hcf +1".parse::<Instruction>(),
+            Err(ParseError::UnknownInstruction("hcf +1".to_string()))
+        );
+        assert_eq!(
+            "acc".parse::<Instruction>(),
+            Err(ParseError::MissingArgument("acc".to_string()))
+        );
+        assert_eq!(
+            "acc +1 junk".parse::<Instruction>(),
+            Err(ParseError::ExtraArgument("acc +1 junk".to_string()))
+        );
+        assert_eq!(
+            "jmp x".parse::<Instruction>(),
+            Err(ParseError::InvalidArgument("jmp x".to_string()))
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Instruction::Accumulate(3).to_string(), "acc +3");
+        assert_eq!(Instruction::Jump(-4).to_string(), "jmp -4");
+        assert_eq!(Instruction::Nop(0).to_string(), "nop +0");
+    }
+
+    #[test]
+    fn flip() {
+        assert_eq!(Instruction::Jump(2).flipped(), Some(Instruction::Nop(2)));
+        assert_eq!(Instruction::Nop(-1).flipped(), Some(Instruction::Jump(-1)));
+        assert_eq!(Instruction::Accumulate(1).flipped(), None);
+    }
+
+    #[test]
+    fn next_address() {
+        assert_eq!(Instruction::Accumulate(5).next_address(3), Some(4));
+        assert_eq!(Instruction::Nop(-5).next_address(3), Some(4));
+        assert_eq!(Instruction::Jump(-5).next_address(3), Some(-2));
+    }
+}
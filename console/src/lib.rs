@@ -0,0 +1,11 @@
+pub mod analysis;
+pub mod debugger;
+pub mod instruction;
+pub mod machine;
+pub mod opcode;
+pub mod program;
+
+pub use instruction::{Instruction, ParseError};
+pub use machine::{Machine, Registers, Stop, Termination, TraceEntry};
+pub use opcode::{InstructionSet, Opcode};
+pub use program::{AssembleError, Program};
@@ -0,0 +1,439 @@
+use crate::instruction::Instruction;
+use crate::opcode::{Cpu, Flow};
+use crate::program::Program;
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+
+/// A snapshot of the machine's registers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Registers {
+    pub pc: usize,
+    /// As wide as the general registers, so custom opcodes can move values
+    /// between them without losing anything.
+    pub acc: i64,
+    /// `a` to `z`, which only custom instructions use.
+    pub general: [i64; 26],
+}
+
+impl Registers {
+    /// Panics if `name` isn't `a` to `z`.
+    pub fn general(&self, name: char) -> i64 {
+        self.general[Self::index(name)]
+    }
+
+    pub fn set_general(&mut self, name: char, value: i64) {
+        self.general[Self::index(name)] = value;
+    }
+
+    fn index(name: char) -> usize {
+        assert!(name.is_ascii_lowercase(), "No register named {}", name);
+        (name as u8 - b'a') as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// Stepped to the address just past the last instruction.
+    Halted,
+    /// Was about to run an instruction for a second time. The registers are
+    /// left as they were before running it.
+    InfiniteLoop,
+    /// A jump from `from` went somewhere other than the program or the
+    /// address just past it. The pc is left on the jump.
+    OutOfBounds { from: usize, target: isize },
+    /// An instruction needs input that isn't there yet. Pushing some more
+    /// lets the machine carry on.
+    AwaitingInput,
+    /// An `acc` would take the accumulator out of range. The registers are
+    /// left as they were before running it.
+    Overflow,
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Termination::Halted => write!(f, "halted"),
+            Termination::InfiniteLoop => write!(f, "infinite loop"),
+            Termination::OutOfBounds { from, target } => {
+                write!(f, "jumped out of bounds from {} to {}", from, target)
+            }
+            Termination::AwaitingInput => write!(f, "waiting for input"),
+            Termination::Overflow => write!(f, "accumulator overflowed"),
+        }
+    }
+}
+
+/// Why `resume` handed control back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// About to run the instruction at this address, which has a breakpoint.
+    Breakpoint(usize),
+    Terminated(Termination),
+}
+
+/// One instruction run while tracing, with the accumulator after running it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: usize,
+    pub instruction: Instruction,
+    pub acc: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Machine<'a> {
+    program: &'a Program,
+    registers: Registers,
+    executed: Vec<bool>,
+    termination: Option<Termination>,
+    breakpoints: BTreeSet<usize>,
+    trace: Option<Vec<TraceEntry>>,
+    detect_loops: bool,
+    input: VecDeque<i64>,
+    output: Vec<i64>,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(program: &'a Program) -> Machine<'a> {
+        Machine {
+            program,
+            registers: Registers::default(),
+            executed: vec![false; program.len()],
+            termination: if program.is_empty() {
+                Some(Termination::Halted)
+            } else {
+                None
+            },
+            breakpoints: BTreeSet::new(),
+            trace: None,
+            detect_loops: true,
+            input: VecDeque::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn program(&self) -> &'a Program {
+        self.program
+    }
+
+    pub fn registers(&self) -> Registers {
+        self.registers
+    }
+
+    /// Why the machine stopped, or `None` if it can keep going.
+    pub fn termination(&self) -> Option<Termination> {
+        self.termination
+    }
+
+    /// Whether the instruction at `address` has been run since the last
+    /// reset.
+    pub fn has_executed(&self, address: usize) -> bool {
+        self.executed.get(address).copied().unwrap_or(false)
+    }
+
+    /// Puts the machine back at the start of the program. Breakpoints are
+    /// kept, as are the tracing and loop detection settings, although the
+    /// trace itself is cleared along with any input and output.
+    pub fn reset(&mut self) {
+        let mut fresh = Machine::new(self.program);
+        fresh.breakpoints = std::mem::take(&mut self.breakpoints);
+        fresh.trace = self.trace.as_ref().map(|_| Vec::new());
+        fresh.detect_loops = self.detect_loops;
+        *self = fresh;
+    }
+
+    /// Whether `run` and `resume` stop when an instruction comes round a
+    /// second time. That's on by default, as the handheld has no other way
+    /// to stop looping, but programs with conditional jumps loop on purpose.
+    pub fn set_loop_detection(&mut self, detect_loops: bool) {
+        self.detect_loops = detect_loops;
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push_back(value);
+        if self.termination == Some(Termination::AwaitingInput) {
+            self.termination = None;
+        }
+    }
+
+    pub fn output(&self) -> &[i64] {
+        &self.output
+    }
+
+    pub fn take_output(&mut self) -> Vec<i64> {
+        std::mem::take(&mut self.output)
+    }
+
+    pub fn set_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    /// Returns whether there was a breakpoint to clear.
+    pub fn clear_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Starts or stops recording every instruction run. Stopping throws
+    /// away what's been recorded so far.
+    pub fn set_tracing(&mut self, tracing: bool) {
+        match (tracing, &self.trace) {
+            (true, None) => self.trace = Some(Vec::new()),
+            (false, Some(_)) => self.trace = None,
+            _ => {}
+        }
+    }
+
+    /// Everything run since tracing started, oldest first.
+    pub fn trace(&self) -> &[TraceEntry] {
+        self.trace.as_deref().unwrap_or(&[])
+    }
+
+    /// Runs a single instruction, returning why the machine stopped if it
+    /// did. Once stopped, stepping does nothing. Stepping never stops with
+    /// `InfiniteLoop`; that's left to `run`.
+    pub fn step(&mut self) -> Option<Termination> {
+        if self.termination.is_some() {
+            return self.termination;
+        }
+
+        let pc = self.registers.pc;
+        let program = self.program;
+        let ins = &program[pc];
+        let next = match ins {
+            Instruction::Accumulate(i) => match self.registers.acc.checked_add(i64::from(*i)) {
+                Some(acc) => {
+                    self.registers.acc = acc;
+                    pc as isize + 1
+                }
+                None => {
+                    self.termination = Some(Termination::Overflow);
+                    return self.termination;
+                }
+            },
+            Instruction::Custom(custom) => {
+                let mut cpu = Cpu {
+                    registers: &mut self.registers,
+                    input: &mut self.input,
+                    output: &mut self.output,
+                };
+                match custom.opcode.execute(&custom.operands, &mut cpu) {
+                    Flow::Next => pc as isize + 1,
+                    Flow::Jump(offset) => pc as isize + offset,
+                    Flow::AwaitInput => {
+                        self.termination = Some(Termination::AwaitingInput);
+                        return self.termination;
+                    }
+                }
+            }
+            _ => ins.next_address(pc).unwrap(),
+        };
+
+        if next < 0 || next as usize > self.program.len() {
+            self.termination = Some(Termination::OutOfBounds {
+                from: pc,
+                target: next,
+            });
+            return self.termination;
+        }
+
+        self.executed[pc] = true;
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceEntry {
+                pc,
+                instruction: ins.clone(),
+                acc: self.registers.acc,
+            });
+        }
+        self.registers.pc = next as usize;
+        if self.registers.pc == self.program.len() {
+            self.termination = Some(Termination::Halted);
+        }
+        self.termination
+    }
+
+    /// Steps until the machine halts, jumps out of bounds or comes back to
+    /// an instruction it's already run. Breakpoints are ignored.
+    pub fn run(&mut self) -> Termination {
+        loop {
+            if let Some(termination) = self.check_loop() {
+                return termination;
+            }
+            self.step();
+        }
+    }
+
+    /// As `run`, but also stopping before any instruction with a breakpoint.
+    /// Always runs at least one instruction, so resuming from a breakpoint
+    /// moves past it.
+    pub fn resume(&mut self) -> Stop {
+        let mut first = true;
+        loop {
+            if let Some(termination) = self.check_loop() {
+                return Stop::Terminated(termination);
+            }
+            let pc = self.registers.pc;
+            if !first && self.breakpoints.contains(&pc) {
+                return Stop::Breakpoint(pc);
+            }
+            first = false;
+            self.step();
+        }
+    }
+
+    // Stops the machine if it's about to repeat itself
+    fn check_loop(&mut self) -> Option<Termination> {
+        if self.detect_loops && self.termination.is_none() && self.executed[self.registers.pc] {
+            self.termination = Some(Termination::InfiniteLoop);
+        }
+        self.termination
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::tests::EXAMPLE;
+
+    #[test]
+    fn infinite_loop() {
+        let program = Program::assemble(EXAMPLE).unwrap();
+        let mut machine = Machine::new(&program);
+        assert_eq!(machine.run(), Termination::InfiniteLoop);
+        assert_eq!(
+            machine.registers(),
+            Registers {
+                pc: 1,
+                acc: 5,
+                ..Default::default()
+            }
+        );
+        assert!(machine.has_executed(7));
+        assert!(!machine.has_executed(5));
+        // Stays stopped
+        assert_eq!(machine.step(), Some(Termination::InfiniteLoop));
+        assert_eq!(machine.registers().acc, 5);
+
+        machine.reset();
+        assert_eq!(machine.registers(), Registers::default());
+        assert_eq!(machine.termination(), None);
+    }
+
+    #[test]
+    fn halts() {
+        let program = Program::assemble(EXAMPLE).unwrap().with_flipped(7).unwrap();
+        let mut machine = Machine::new(&program);
+        assert_eq!(machine.step(), None);
+        assert_eq!(machine.step(), None);
+        assert_eq!(
+            machine.registers(),
+            Registers {
+                pc: 2,
+                acc: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(machine.run(), Termination::Halted);
+        assert_eq!(
+            machine.registers(),
+            Registers {
+                pc: 9,
+                acc: 8,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let program = Program::assemble("acc +1\njmp -2").unwrap();
+        let mut machine = Machine::new(&program);
+        assert_eq!(
+            machine.run(),
+            Termination::OutOfBounds {
+                from: 1,
+                target: -1
+            }
+        );
+        assert_eq!(
+            machine.registers(),
+            Registers {
+                pc: 1,
+                acc: 1,
+                ..Default::default()
+            }
+        );
+
+        let program = Program::assemble("jmp +3\nnop +0").unwrap();
+        assert_eq!(
+            Machine::new(&program).run(),
+            Termination::OutOfBounds { from: 0, target: 3 }
+        );
+
+        // Jumping to just past the end is a normal halt
+        let program = Program::assemble("jmp +2\nnop +0").unwrap();
+        assert_eq!(Machine::new(&program).run(), Termination::Halted);
+        assert_eq!(Machine::new(&Program::default()).run(), Termination::Halted);
+    }
+
+    #[test]
+    fn breakpoints() {
+        let program = Program::assemble(EXAMPLE).unwrap();
+        let mut machine = Machine::new(&program);
+        machine.set_breakpoint(3);
+        machine.set_breakpoint(6);
+        assert_eq!(machine.breakpoints().collect::<Vec<_>>(), vec![3, 6]);
+
+        assert_eq!(machine.resume(), Stop::Breakpoint(6));
+        assert_eq!(
+            machine.registers(),
+            Registers {
+                pc: 6,
+                acc: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(machine.resume(), Stop::Breakpoint(3));
+        assert!(machine.clear_breakpoint(3));
+        assert!(!machine.clear_breakpoint(3));
+        assert_eq!(
+            machine.resume(),
+            Stop::Terminated(Termination::InfiniteLoop)
+        );
+        assert_eq!(machine.registers().acc, 5);
+
+        // Starting on a breakpoint doesn't stop straight away
+        machine.reset();
+        assert_eq!(machine.breakpoints().count(), 1);
+        machine.set_breakpoint(0);
+        assert_eq!(machine.resume(), Stop::Breakpoint(6));
+    }
+
+    #[test]
+    fn tracing() {
+        let program = Program::assemble(EXAMPLE).unwrap();
+        let mut machine = Machine::new(&program);
+        machine.step();
+        machine.set_tracing(true);
+        machine.run();
+        let pcs: Vec<usize> = machine.trace().iter().map(|t| t.pc).collect();
+        assert_eq!(pcs, vec![1, 2, 6, 7, 3, 4]);
+        assert_eq!(
+            machine.trace()[4],
+            TraceEntry {
+                pc: 3,
+                instruction: Instruction::Accumulate(3),
+                acc: 5
+            }
+        );
+
+        machine.reset();
+        assert!(machine.trace().is_empty());
+        machine.step();
+        assert_eq!(machine.trace().len(), 1);
+        machine.set_tracing(false);
+        assert!(machine.trace().is_empty());
+    }
+}
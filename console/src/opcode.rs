@@ -0,0 +1,402 @@
+use crate::instruction::{Instruction, ParseError};
+use crate::machine::Registers;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// An argument to an opcode outside the built-in set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Value(i64),
+    /// One of the general purpose registers, `a` to `z`.
+    Register(char),
+    Accumulator,
+}
+
+impl FromStr for Operand {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            _ if s == "acc" => Ok(Operand::Accumulator),
+            (Some(c), None) if c.is_ascii_lowercase() => Ok(Operand::Register(c)),
+            _ => s.parse().map(Operand::Value).map_err(|_| ()),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Value(v) => write!(f, "{}", v),
+            Operand::Register(c) => write!(f, "{}", c),
+            Operand::Accumulator => write!(f, "acc"),
+        }
+    }
+}
+
+/// The parts of the machine an opcode can use while it runs.
+pub struct Cpu<'a> {
+    pub registers: &'a mut Registers,
+    pub input: &'a mut VecDeque<i64>,
+    pub output: &'a mut Vec<i64>,
+}
+
+impl<'a> Cpu<'a> {
+    pub fn read(&self, operand: &Operand) -> i64 {
+        match operand {
+            Operand::Value(v) => *v,
+            Operand::Register(c) => self.registers.general(*c),
+            Operand::Accumulator => self.registers.acc,
+        }
+    }
+
+    /// Writing to a literal value does nothing.
+    pub fn write(&mut self, operand: &Operand, value: i64) {
+        match operand {
+            Operand::Value(_) => {}
+            Operand::Register(c) => self.registers.set_general(*c, value),
+            Operand::Accumulator => self.registers.acc = value,
+        }
+    }
+}
+
+/// Where to go after running an opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Next,
+    /// Relative to the opcode's own address.
+    Jump(isize),
+    /// Can't run until there's more input. Opcodes have to check for this
+    /// before changing anything, as they'll be run again from scratch.
+    AwaitInput,
+}
+
+pub trait Opcode: fmt::Debug {
+    fn mnemonic(&self) -> &str;
+
+    /// How many operands the opcode takes.
+    fn arity(&self) -> usize;
+
+    fn execute(&self, operands: &[Operand], cpu: &mut Cpu<'_>) -> Flow;
+}
+
+/// An instruction using an opcode from outside the built-in set.
+#[derive(Debug, Clone)]
+pub struct Custom {
+    pub opcode: Rc<dyn Opcode>,
+    pub operands: Vec<Operand>,
+}
+
+impl PartialEq for Custom {
+    fn eq(&self, other: &Self) -> bool {
+        self.opcode.mnemonic() == other.opcode.mnemonic() && self.operands == other.operands
+    }
+}
+
+impl Eq for Custom {}
+
+impl fmt::Display for Custom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.opcode.mnemonic())?;
+        for operand in &self.operands {
+            write!(f, " {}", operand)?;
+        }
+        Ok(())
+    }
+}
+
+type Builtin = fn(i32) -> Instruction;
+
+// The handheld's own instructions, which all take a single signed argument
+const BUILTINS: [(&str, Builtin); 3] = [
+    ("acc", Instruction::Accumulate),
+    ("jmp", Instruction::Jump),
+    ("nop", Instruction::Nop),
+];
+
+/// The table of opcodes the parser knows about. Starts with the handheld's
+/// `acc`, `jmp` and `nop`; registered opcodes are looked up first, so they
+/// can replace those too.
+#[derive(Debug, Clone, Default)]
+pub struct InstructionSet {
+    opcodes: HashMap<String, Rc<dyn Opcode>>,
+}
+
+impl InstructionSet {
+    pub fn new() -> InstructionSet {
+        Default::default()
+    }
+
+    pub fn register<O: Opcode + 'static>(&mut self, opcode: O) -> &mut Self {
+        self.opcodes
+            .insert(opcode.mnemonic().to_string(), Rc::new(opcode));
+        self
+    }
+
+    /// As `register`, for building up a set in one expression.
+    pub fn with<O: Opcode + 'static>(mut self, opcode: O) -> Self {
+        self.register(opcode);
+        self
+    }
+
+    pub fn mnemonics(&self) -> impl Iterator<Item = &str> {
+        self.opcodes
+            .keys()
+            .map(|m| m.as_str())
+            .chain(BUILTINS.iter().map(|(m, _)| *m))
+    }
+
+    /// Parses one instruction. Operands can be separated by commas as well
+    /// as whitespace.
+    pub fn parse(&self, s: &str) -> Result<Instruction, ParseError> {
+        let mut it = s
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|part| !part.is_empty());
+        let ins_str = it.next().ok_or(ParseError::Empty)?;
+
+        if let Some(opcode) = self.opcodes.get(ins_str) {
+            let operands = it
+                .map(|part| {
+                    part.parse()
+                        .map_err(|_| ParseError::InvalidArgument(s.to_string()))
+                })
+                .collect::<Result<Vec<Operand>, _>>()?;
+            return match operands.len() {
+                n if n < opcode.arity() => Err(ParseError::MissingArgument(s.to_string())),
+                n if n > opcode.arity() => Err(ParseError::ExtraArgument(s.to_string())),
+                _ => Ok(Instruction::Custom(Custom {
+                    opcode: opcode.clone(),
+                    operands,
+                })),
+            };
+        }
+
+        let build = BUILTINS
+            .iter()
+            .find(|(m, _)| *m == ins_str)
+            .map(|(_, build)| build)
+            .ok_or_else(|| ParseError::UnknownInstruction(s.to_string()))?;
+        let arg = match it.next() {
+            Some(arg_str) => arg_str
+                .parse()
+                .map_err(|_| ParseError::InvalidArgument(s.to_string())),
+            None => Err(ParseError::MissingArgument(s.to_string())),
+        }?;
+        if it.next().is_some() {
+            return Err(ParseError::ExtraArgument(s.to_string()));
+        }
+        Ok(build(arg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::{Machine, Termination};
+    use crate::program::Program;
+
+    // A few opcodes in the style of other years' puzzles
+
+    #[derive(Debug)]
+    struct Cpy;
+
+    impl Opcode for Cpy {
+        fn mnemonic(&self) -> &str {
+            "cpy"
+        }
+
+        fn arity(&self) -> usize {
+            2
+        }
+
+        fn execute(&self, operands: &[Operand], cpu: &mut Cpu<'_>) -> Flow {
+            let value = cpu.read(&operands[0]);
+            cpu.write(&operands[1], value);
+            Flow::Next
+        }
+    }
+
+    #[derive(Debug)]
+    struct Offset(&'static str, i64);
+
+    impl Opcode for Offset {
+        fn mnemonic(&self) -> &str {
+            self.0
+        }
+
+        fn arity(&self) -> usize {
+            1
+        }
+
+        fn execute(&self, operands: &[Operand], cpu: &mut Cpu<'_>) -> Flow {
+            let value = cpu.read(&operands[0]);
+            cpu.write(&operands[0], value + self.1);
+            Flow::Next
+        }
+    }
+
+    #[derive(Debug)]
+    struct Jnz;
+
+    impl Opcode for Jnz {
+        fn mnemonic(&self) -> &str {
+            "jnz"
+        }
+
+        fn arity(&self) -> usize {
+            2
+        }
+
+        fn execute(&self, operands: &[Operand], cpu: &mut Cpu<'_>) -> Flow {
+            if cpu.read(&operands[0]) != 0 {
+                Flow::Jump(cpu.read(&operands[1]) as isize)
+            } else {
+                Flow::Next
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct In;
+
+    impl Opcode for In {
+        fn mnemonic(&self) -> &str {
+            "in"
+        }
+
+        fn arity(&self) -> usize {
+            1
+        }
+
+        fn execute(&self, operands: &[Operand], cpu: &mut Cpu<'_>) -> Flow {
+            match cpu.input.pop_front() {
+                Some(value) => {
+                    cpu.write(&operands[0], value);
+                    Flow::Next
+                }
+                None => Flow::AwaitInput,
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct Out;
+
+    impl Opcode for Out {
+        fn mnemonic(&self) -> &str {
+            "out"
+        }
+
+        fn arity(&self) -> usize {
+            1
+        }
+
+        fn execute(&self, operands: &[Operand], cpu: &mut Cpu<'_>) -> Flow {
+            let value = cpu.read(&operands[0]);
+            cpu.output.push(value);
+            Flow::Next
+        }
+    }
+
+    fn instruction_set() -> InstructionSet {
+        InstructionSet::new()
+            .with(Cpy)
+            .with(Offset("inc", 1))
+            .with(Offset("dec", -1))
+            .with(Jnz)
+            .with(In)
+            .with(Out)
+    }
+
+    #[test]
+    fn parse() {
+        let set = instruction_set();
+        let ins = set.parse("cpy 41, a").unwrap();
+        assert_eq!(ins.to_string(), "cpy 41 a");
+        assert_eq!(set.parse(&ins.to_string()), Ok(ins));
+        assert_eq!(set.parse("jmp +2"), Ok(Instruction::Jump(2)));
+        assert_eq!(
+            set.parse("jmp +2, a"),
+            Err(ParseError::ExtraArgument("jmp +2, a".to_string()))
+        );
+        assert_eq!(set.parse("inc acc").unwrap().to_string(), "inc acc");
+        assert_eq!(
+            set.parse("cpy 1"),
+            Err(ParseError::MissingArgument("cpy 1".to_string()))
+        );
+        assert_eq!(
+            set.parse("inc a b"),
+            Err(ParseError::ExtraArgument("inc a b".to_string()))
+        );
+        assert_eq!(
+            set.parse("inc ab"),
+            Err(ParseError::InvalidArgument("inc ab".to_string()))
+        );
+        assert_eq!(
+            InstructionSet::new().parse("inc a"),
+            Err(ParseError::UnknownInstruction("inc a".to_string()))
+        );
+
+        let mut mnemonics: Vec<&str> = set.mnemonics().collect();
+        mnemonics.sort_unstable();
+        assert_eq!(
+            mnemonics,
+            vec!["acc", "cpy", "dec", "in", "inc", "jmp", "jnz", "nop", "out"]
+        );
+    }
+
+    #[test]
+    fn registers_and_jumps() {
+        // The assembunny example from 2016 day 12, then a counted loop
+        let source = "cpy 41 a\ninc a\ninc a\ndec a\njnz a 2\ndec a\ncpy a b\ncpy 3 c\n\
+                      acc +2\ndec c\njnz c -2\nout acc";
+        let program = Program::assemble_with(source, &instruction_set()).unwrap();
+        let mut machine = Machine::new(&program);
+        // Loops by design, so don't stop at the first repeat
+        machine.set_loop_detection(false);
+        assert_eq!(machine.run(), Termination::Halted);
+        assert_eq!(machine.registers().general('a'), 42);
+        assert_eq!(machine.registers().general('b'), 42);
+        assert_eq!(machine.registers().acc, 6);
+        assert_eq!(machine.output(), &[6]);
+        assert_eq!(program.disassemble().lines().next(), Some("cpy 41 a"));
+    }
+
+    #[test]
+    fn input() {
+        let source = "in a\nin b\ncpy a acc\nacc +1\nout acc\nout b";
+        let program = Program::assemble_with(source, &instruction_set()).unwrap();
+        let mut machine = Machine::new(&program);
+        machine.push_input(5);
+        assert_eq!(machine.run(), Termination::AwaitingInput);
+        assert_eq!(machine.registers().pc, 1);
+        assert_eq!(machine.registers().general('a'), 5);
+
+        machine.push_input(9);
+        assert_eq!(machine.run(), Termination::Halted);
+        assert_eq!(machine.take_output(), vec![6, 9]);
+        assert!(machine.output().is_empty());
+    }
+
+    #[test]
+    fn wide_accumulator() {
+        let source = "cpy 8589934592 acc
+acc -1
+cpy acc a";
+        let program = Program::assemble_with(source, &instruction_set()).unwrap();
+        let mut machine = Machine::new(&program);
+        assert_eq!(machine.run(), Termination::Halted);
+        assert_eq!(machine.registers().general('a'), (1 << 33) - 1);
+
+        let source = "cpy 9223372036854775806 acc\nacc +1\nacc +1";
+        let program = Program::assemble_with(source, &instruction_set()).unwrap();
+        let mut machine = Machine::new(&program);
+        assert_eq!(machine.run(), Termination::Overflow);
+        assert_eq!(machine.registers().pc, 2);
+        assert_eq!(machine.registers().acc, i64::MAX);
+        assert_eq!(Termination::Overflow.to_string(), "accumulator overflowed");
+    }
+}
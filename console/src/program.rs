@@ -0,0 +1,154 @@
+use crate::instruction::{Instruction, ParseError};
+use crate::opcode::InstructionSet;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("Line {line}: {error}")]
+pub struct AssembleError {
+    /// 1-based, counting blank lines.
+    pub line: usize,
+    #[source]
+    pub error: ParseError,
+}
+
+/// A boot code program, one instruction per address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+}
+
+impl Program {
+    pub fn new(instructions: Vec<Instruction>) -> Program {
+        Program { instructions }
+    }
+
+    /// Parses source text with one instruction per line. Blank lines are
+    /// skipped.
+    pub fn assemble(source: &str) -> Result<Program, AssembleError> {
+        Program::assemble_with(source, &InstructionSet::new())
+    }
+
+    /// As `assemble`, but for programs using opcodes from `set`.
+    pub fn assemble_with(source: &str, set: &InstructionSet) -> Result<Program, AssembleError> {
+        source
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                set.parse(line)
+                    .map_err(|error| AssembleError { line: i + 1, error })
+            })
+            .collect()
+    }
+
+    /// The program as source text, which assembles back to the same program
+    /// given the same instruction set.
+    pub fn disassemble(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    pub fn get(&self, address: usize) -> Option<&Instruction> {
+        self.instructions.get(address)
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// A copy of the program with the instruction at `address` flipped, if
+    /// there's a flippable instruction there.
+    pub fn with_flipped(&self, address: usize) -> Option<Program> {
+        let flipped = self.get(address)?.flipped()?;
+        let mut program = self.clone();
+        program.instructions[address] = flipped;
+        Some(program)
+    }
+}
+
+impl FromStr for Program {
+    type Err = AssembleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Program::assemble(s)
+    }
+}
+
+impl FromIterator<Instruction> for Program {
+    fn from_iter<I: IntoIterator<Item = Instruction>>(iter: I) -> Self {
+        Program::new(iter.into_iter().collect())
+    }
+}
+
+impl Index<usize> for Program {
+    type Output = Instruction;
+
+    fn index(&self, address: usize) -> &Instruction {
+        &self.instructions[address]
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ins in &self.instructions {
+            writeln!(f, "{}", ins)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub const EXAMPLE: &str = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6
+";
+
+    #[test]
+    fn round_trip() {
+        let program = Program::assemble(EXAMPLE).unwrap();
+        assert_eq!(program.len(), 9);
+        assert_eq!(program[2], Instruction::Jump(4));
+        assert_eq!(program.disassemble(), EXAMPLE);
+        assert_eq!(program.disassemble().parse(), Ok(program));
+    }
+
+    #[test]
+    fn assemble_errors() {
+        let program: Program = "acc +1\n\n  \njmp -1".parse().unwrap();
+        assert_eq!(program.len(), 2);
+
+        let err = Program::assemble("acc +1\n\nbad +2").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert_eq!(err.to_string(), "Line 3: Unknown instruction: bad +2");
+    }
+
+    #[test]
+    fn flipping() {
+        let program = Program::assemble(EXAMPLE).unwrap();
+        let flipped = program.with_flipped(7).unwrap();
+        assert_eq!(flipped[7], Instruction::Nop(-4));
+        assert_eq!(program[7], Instruction::Jump(-4));
+        assert_eq!(program.with_flipped(1), None);
+        assert_eq!(program.with_flipped(9), None);
+    }
+}
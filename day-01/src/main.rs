@@ -1,30 +1,5 @@
 use commons::io::load_file_lines;
-use std::cmp::Ordering;
-
-fn sum_to_target(ints: &[u32], target: u32) -> Option<(u32, u32)> {
-    let mut lower_idx = 0;
-    let mut upper_idx = ints.len() - 1;
-    while lower_idx != upper_idx {
-        let lower = ints[lower_idx];
-        let upper = ints[upper_idx];
-
-        let sum = lower + upper;
-        match sum.cmp(&target) {
-            Ordering::Equal => {
-                return Some((lower, upper));
-            }
-            Ordering::Less => {
-                // Move the lower bound up to make bigger numbers
-                lower_idx += 1;
-            }
-            Ordering::Greater => {
-                // Move the upper bound down to make smaller numbers
-                upper_idx -= 1;
-            }
-        }
-    }
-    None
-}
+use commons::ksum::KSum;
 
 fn main() {
     let target = 2020;
@@ -33,17 +8,15 @@ fn main() {
         .collect();
     ints.sort_unstable();
     // Part 1
-    let part1 = sum_to_target(&ints[..], 2020);
-    match part1 {
-        Some((a, b)) => println!("{} * {} = {}", a, b, a * b),
+    let part1 = KSum::new(&ints, 2).first_values(target);
+    match part1.as_deref() {
+        Some([a, b]) => println!("{} * {} = {}", a, b, a * b),
         _ => println!("Noooes"),
     }
 
     // Part 2
-    for (i, c) in ints.iter().enumerate() {
-        let remain = &ints[i..];
-        let part_target = target - c;
-        if let Some((a, b)) = sum_to_target(remain, part_target) {
+    for values in KSum::new(&ints, 3).values(target) {
+        if let [c, a, b] = values[..] {
             println!("{} * {} * {} = {}", c, a, b, c * a * b);
         }
     }
@@ -1,3 +1,4 @@
+use commons::grid::{Grid, VecGrid, WrappingGrid};
 use commons::io::load_file_lines;
 use std::str::FromStr;
 use thiserror::Error;
@@ -37,61 +38,25 @@ impl FromStr for Row {
     }
 }
 
-struct Grid {
-    rows: Vec<Row>,
-    width: usize,
-}
-
-impl Grid {
-    pub fn new(rows: Vec<Row>) -> Grid {
-        let width = rows[0].cells.len();
-        Grid { rows, width }
-    }
-
-    pub fn from_iter<E>(it: impl Iterator<Item = Result<Row, E>>) -> Result<Grid, E> {
-        let mut rows = Vec::new();
-        for res in it {
-            let row = res?;
-            rows.push(row);
-        }
-
-        Ok(Grid::new(rows))
-    }
-
-    pub fn height(&self) -> usize {
-        self.rows.len()
-    }
-
-    pub fn at(&self, x: usize, y: usize) -> &Cell {
-        let row = &self.rows[y];
-        &row.cells[x % self.width]
-    }
-
-    pub fn trees_hit(&self, stride: (usize, usize)) -> u32 {
-        let mut pos = (0, 0);
-        let mut tree_count = 0;
-        while pos.1 < self.height() {
-            tree_count += match self.at(pos.0, pos.1) {
-                Cell::Tree => 1,
-                Cell::Clear => 0,
-            };
-            pos = (pos.0 + stride.0, pos.1 + stride.1)
-        }
-        tree_count
-    }
+fn trees_hit(forest: WrappingGrid<Cell>, stride: (isize, isize)) -> u32 {
+    forest
+        .walk((0, 0), stride)
+        .filter(|cell| matches!(cell, Cell::Tree))
+        .count() as u32
 }
 
 fn main() {
-    let row_iter = load_file_lines::<Row>("input.txt");
-    let grid = Grid::from_iter(row_iter).unwrap();
+    let rows = load_file_lines::<Row>("input.txt").map(|res| res.unwrap().cells);
+    let grid = VecGrid::from_rows(rows);
+    let forest = WrappingGrid::horizontal(&grid);
 
-    let part1 = grid.trees_hit((3, 1));
+    let part1 = trees_hit(forest, (3, 1));
     println!("{}", part1);
 
     let part2_parts = vec![(1, 1), (5, 1), (7, 1), (1, 2)];
     let part2 = part2_parts
         .iter()
-        .map(|stride| grid.trees_hit(*stride))
+        .map(|stride| trees_hit(forest, *stride))
         .product::<u32>();
     // Part 2's parts include part 1, but we intentionally don't do it
     // again. Instead, we multiply it here.
@@ -1,4 +1,5 @@
 use commons::io::load_file_lines;
+use commons::math::crt;
 
 fn main() {
     let mut input = load_file_lines::<String>("input.txt").map(|x| x.unwrap());
@@ -25,28 +26,12 @@ fn main() {
         }
     }
 
-    let mut t: u64 = 0;
-    loop {
-        let mut found = true;
-        let mut incr = 1;
-        for (i, opt_id) in bus_ids.iter().enumerate() {
-            if let Some(id) = opt_id {
-                let bus_ts = t + i as u64;
-                if bus_ts % (*id as u64) != 0 {
-                    found = false;
-                    break;
-                } else {
-                    // The next common place will be at least the multiple of all the currently
-                    // matching IDs, there aren't any places sooner where the multiples align.
-                    incr *= *id as u64
-                }
-            }
-        }
-
-        if found {
-            println!("{}", t);
-            break;
-        }
-        t += incr;
-    }
+    // Bus at offset i must depart at t + i, so t ≡ -i (mod id)
+    let congruences: Vec<(i64, u64)> = bus_ids
+        .iter()
+        .enumerate()
+        .filter_map(|(i, opt_id)| opt_id.map(|id| (-(i as i64), id as u64)))
+        .collect();
+    let (t, _) = crt(&congruences).expect("Buses never line up");
+    println!("{}", t);
 }
@@ -1,62 +1,68 @@
+use commons::collections::RecencyMap;
 use commons::io::load_file_lines;
-use std::collections::{HashMap, VecDeque};
-use std::hash::Hash;
+use std::env;
 
-#[derive(Debug)]
-struct MostRecent<T> {
-    q: VecDeque<T>,
-    n: usize,
+/// Remembers the turn each number was last spoken on.
+trait LastSeen {
+    /// Records `value` as spoken on `turn`, returning the turn it was
+    /// previously spoken on, if any.
+    fn speak(&mut self, value: u32, turn: u32) -> Option<u32>;
 }
 
-impl<T> MostRecent<T> {
-    pub fn new(n: usize) -> MostRecent<T> {
-        MostRecent {
-            q: VecDeque::with_capacity(n + 1),
-            n,
-        }
+impl LastSeen for RecencyMap<u32, u32> {
+    fn speak(&mut self, value: u32, turn: u32) -> Option<u32> {
+        // Only keeping one turn per number, so whatever gets pushed out is the
+        // previous one
+        self.insert(value, turn)
     }
+}
 
-    pub fn push(&mut self, val: T) {
-        self.q.push_front(val);
-        // Get us back to where we want to be
-        while self.q.len() > self.n {
-            self.q.pop_back();
-        }
-    }
+/// A flat table indexed by the number, holding one more than the turn it was
+/// last spoken on so that zero can mean never.
+struct DenseLastSeen(Vec<u32>);
 
-    pub fn get(&self, i: usize) -> Option<&T> {
-        self.q.get(i)
+impl DenseLastSeen {
+    fn new(size: usize) -> DenseLastSeen {
+        DenseLastSeen(vec![0; size])
     }
 }
 
-#[derive(Debug)]
-struct RecencyMap<K, V> {
-    map: HashMap<K, MostRecent<V>>,
-    n: usize,
-}
-
-impl<K, V> RecencyMap<K, V>
-where
-    K: Eq + Hash + Copy,
-{
-    pub fn new(n: usize) -> RecencyMap<K, V> {
-        RecencyMap {
-            map: HashMap::new(),
-            n,
+impl LastSeen for DenseLastSeen {
+    fn speak(&mut self, value: u32, turn: u32) -> Option<u32> {
+        match std::mem::replace(&mut self.0[value as usize], turn + 1) {
+            0 => None,
+            prev => Some(prev - 1),
         }
     }
+}
 
-    fn get_key(&mut self, key: &K) -> &mut MostRecent<V> {
-        let n = self.n;
-        self.map.entry(*key).or_insert_with(|| MostRecent::new(n))
+fn play_with<S: LastSeen>(mut seen: S, starting: &[u32], turns: u32) -> Option<u32> {
+    let (&first_last, before) = starting.split_last()?;
+    if turns as usize <= starting.len() {
+        return starting.get((turns as usize).checked_sub(1)?).copied();
     }
 
-    pub fn insert(&mut self, key: K, value: V) {
-        self.get_key(&key).push(value)
+    for (turn, val) in before.iter().enumerate() {
+        seen.speak(*val, turn as u32);
     }
+    let mut last = first_last;
+    for turn in before.len() as u32..turns - 1 {
+        last = match seen.speak(last, turn) {
+            Some(prev) => turn - prev,
+            None => 0,
+        };
+    }
+    Some(last)
+}
 
-    pub fn get(&self, key: &K, nth: usize) -> Option<&V> {
-        self.map.get(key).map(|recent| recent.get(nth)).flatten()
+/// The number spoken on the `turns`th turn. Every number spoken after the
+/// starting ones is a gap between two turns, so if the starting numbers are
+/// below the turn count too then a flat table covers everything.
+fn play(starting: &[u32], turns: u32) -> Option<u32> {
+    if starting.iter().all(|&v| v < turns) {
+        play_with(DenseLastSeen::new(turns as usize), starting, turns)
+    } else {
+        play_with(RecencyMap::new(1), starting, turns)
     }
 }
 
@@ -69,27 +75,57 @@ fn main() {
         .map(|res| res.parse().unwrap())
         .collect();
 
-    let mut last_occurance: RecencyMap<u32, usize> = RecencyMap::new(2);
-    let mut last_number = 0;
-    for (i, val) in input.iter().enumerate() {
-        last_occurance.insert(*val, i);
-        last_number = *val;
+    let mut targets: Vec<u32> = env::args()
+        .skip(1)
+        .map(|arg| arg.parse().unwrap())
+        .collect();
+    if targets.is_empty() {
+        targets = vec![2020, 30000000];
     }
 
-    for i in input.len()..30000000 {
-        let next_number = match (
-            last_occurance.get(&last_number, 0),
-            last_occurance.get(&last_number, 1),
-        ) {
-            (Some(a), Some(b)) => (a - b) as u32,
-            _ => 0,
-        };
-        last_occurance.insert(next_number, i);
-        last_number = next_number;
-        if i == 2019 {
-            println!("{}", last_number);
+    for turns in targets {
+        println!("{}", play(&input, turns).unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() {
+        let cases: Vec<(&[u32], u32)> = vec![
+            (&[0, 3, 6], 436),
+            (&[1, 3, 2], 1),
+            (&[2, 1, 3], 10),
+            (&[1, 2, 3], 27),
+            (&[2, 3, 1], 78),
+            (&[3, 2, 1], 438),
+            (&[3, 1, 2], 1836),
+        ];
+        for (starting, expected) in cases {
+            assert_eq!(play(starting, 2020), Some(expected));
+            assert_eq!(
+                play_with(RecencyMap::new(1), starting, 2020),
+                Some(expected)
+            );
         }
     }
 
-    println!("{}", last_number);
+    #[test]
+    fn early_turns() {
+        let starting = [0, 3, 6];
+        let spoken: Vec<u32> = (1..=10).map(|t| play(&starting, t).unwrap()).collect();
+        assert_eq!(spoken, vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0]);
+        assert_eq!(play(&starting, 0), None);
+        assert_eq!(play(&[], 5), None);
+    }
+
+    #[test]
+    fn large_starting_numbers() {
+        // Too big for the dense table, so falls back to the map
+        assert_eq!(play(&[1000, 5], 3), Some(0));
+        assert_eq!(play(&[1000, 5], 5), Some(1));
+        assert_eq!(play(&[1000, 5], 6), Some(0));
+    }
 }
@@ -1,7 +1,7 @@
 use bitvec::prelude::*;
+use commons::assign::assign;
 use commons::io::load_file_lines;
-use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 struct BitRanges {
@@ -125,38 +125,21 @@ impl Input {
             }
         }
 
-        let mut possible_field_names = Vec::new();
-        for field_values in fields {
-            let possibles: HashSet<&String> = self
+        let possible_field_names = fields.iter().enumerate().map(|(column, field_values)| {
+            let possibles: Vec<&String> = self
                 .rules
                 .iter()
                 .filter(|(_, rule_range)| numbers_in_range(field_values.iter(), rule_range))
                 .map(|(name, _)| name)
                 .collect();
-            possible_field_names.push(possibles);
-        }
-
-        let mut field_names = HashMap::new();
-        for (i, possible_names) in possible_field_names
-            .iter()
-            .enumerate()
-            .sorted_by_key(|(_, p)| p.len())
-        {
-            let real_possibles: Vec<&&String> = possible_names
-                .iter()
-                .filter(|&x| !field_names.contains_key(x))
-                .collect();
-            if real_possibles.len() == 1 {
-                field_names.insert(*real_possibles[0], i);
-            } else {
-                println!("Ambigious: {:?}", real_possibles);
-            }
-        }
+            (column, possibles)
+        });
+        let field_names = assign(possible_field_names).unwrap();
 
         field_names
             .iter()
-            .filter(|(name, _)| name.contains("departure"))
-            .map(|(_, column)| self.our_ticket[*column])
+            .filter(|(_, name)| name.contains("departure"))
+            .map(|(column, _)| self.our_ticket[*column])
             .fold(1 as u128, |a, x| a * (x as u128))
     }
 }
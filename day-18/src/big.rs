@@ -0,0 +1,75 @@
+use crate::types::{BinOp, EvalError, EvalErrorKind, Expr};
+use num_bigint::{BigInt, Sign};
+use std::convert::TryFrom;
+
+impl BinOp {
+    /// As `apply`, but can only overflow on exponents too big for a `u32`.
+    pub fn apply_big(self, a: &BigInt, b: &BigInt) -> Result<BigInt, EvalErrorKind> {
+        let result = match self {
+            BinOp::Add => a + b,
+            BinOp::Sub => a - b,
+            BinOp::Mul => a * b,
+            BinOp::Div | BinOp::Rem if b.sign() == Sign::NoSign => {
+                return Err(EvalErrorKind::DivisionByZero)
+            }
+            BinOp::Div => a / b,
+            BinOp::Rem => a % b,
+            BinOp::Pow if b.sign() == Sign::Minus => return Err(EvalErrorKind::NegativeExponent),
+            BinOp::Pow => a.pow(u32::try_from(b).map_err(|_| EvalErrorKind::Overflow)?),
+        };
+        Ok(result)
+    }
+}
+
+impl Expr {
+    /// As `evaluate`, with arbitrary precision.
+    pub fn evaluate_big(&self) -> Result<BigInt, EvalError> {
+        match self {
+            Expr::Number(n) => Ok(BigInt::from(*n)),
+            Expr::Neg(a) => Ok(-a.evaluate_big()?),
+            Expr::Binary(op, a, b) => op
+                .apply_big(&a.evaluate_big()?, &b.evaluate_big()?)
+                .map_err(|kind| EvalError {
+                    kind,
+                    expr: self.clone(),
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pratt::PrattParser;
+    use crate::precedence::Precedence;
+
+    #[test]
+    fn no_overflow() {
+        let parser = PrattParser::new(Precedence::standard());
+        let expr = parser
+            .parse("3037000500 * 3037000500 - -9223372036854775807 - 1")
+            .unwrap();
+        assert!(expr.evaluate().is_err());
+        assert_eq!(
+            expr.evaluate_big().unwrap().to_string(),
+            "18446744073855025806"
+        );
+        let expr = parser.parse("-(2 ^ 100) / 3 % 1000").unwrap();
+        assert_eq!(expr.evaluate_big(), Ok(BigInt::from(-125)));
+    }
+
+    #[test]
+    fn errors() {
+        let parser = PrattParser::new(Precedence::standard());
+        let err = parser
+            .parse("2 ^ 100 % (5 - 5)")
+            .unwrap()
+            .evaluate_big()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Division by zero in ((2 ^ 100) % (5 - 5))");
+        assert_eq!(
+            BinOp::Pow.apply_big(&BigInt::from(2), &BigInt::from(-1)),
+            Err(EvalErrorKind::NegativeExponent)
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use day_18::parse;
+use day_18::pratt::{PrattError, PrattParser};
+use day_18::precedence::Precedence;
+use day_18::types::Expr;
+use std::io::{self, BufRead, Write};
+
+fn prompt() {
+    print!("> ");
+    io::stdout().flush().unwrap();
+}
+
+fn show<E>(name: &str, line: &str, parsed: Result<Expr, E>, precedence: &Precedence)
+where
+    E: Into<PrattError>,
+{
+    match parsed.map_err(Into::into) {
+        Ok(expr) => match expr.evaluate() {
+            Ok(value) => println!("{:<16}{} = {}", name, expr.minimal(precedence), value),
+            Err(e) => println!("{:<16}{}", name, e),
+        },
+        Err(e) => {
+            println!("{:<16}{}", name, e);
+            println!("{:<16}{}", "", line);
+            println!("{:<16}{}", "", e.underline());
+        }
+    }
+}
+
+fn main() {
+    let standard = PrattParser::new(Precedence::standard());
+    prompt();
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap();
+        if !line.trim().is_empty() {
+            show(
+                "left to right",
+                &line,
+                parse::left_to_right(&line),
+                &Precedence::left_to_right(),
+            );
+            show(
+                "addition first",
+                &line,
+                parse::addition_first(&line),
+                &Precedence::addition_first(),
+            );
+            show(
+                "standard",
+                &line,
+                standard.parse(&line),
+                standard.precedence(),
+            );
+        }
+        prompt();
+    }
+    println!();
+}
@@ -0,0 +1,184 @@
+use crate::types::BinOp;
+use std::fmt;
+use std::iter::{Enumerate, Peekable};
+use std::ops::Range;
+use std::str::CharIndices;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    Number(i64),
+    /// `-` is always lexed as an operator. It's up to the parser whether it
+    /// can be negation.
+    Operator(BinOp),
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "number: {}", n),
+            Token::Operator(op) => write!(f, "operator: {}", op),
+            Token::Open => write!(f, "opening bracket"),
+            Token::Close => write!(f, "closing bracket"),
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxErrorKind {
+    #[error("Unexpected end of expression")]
+    UnexpectedEnd,
+    #[error("Unexpected {0}")]
+    UnexpectedToken(Token),
+    #[error("Unexpected character: {0}")]
+    UnexpectedChar(char),
+    #[error("Number out of range: {0}")]
+    InvalidNumber(String),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Column {}: {kind}", .columns.start + 1)]
+pub struct SyntaxError {
+    pub kind: SyntaxErrorKind,
+    /// 0-based, counting characters rather than bytes.
+    pub columns: Range<usize>,
+}
+
+impl SyntaxError {
+    pub fn new(kind: SyntaxErrorKind, columns: Range<usize>) -> SyntaxError {
+        SyntaxError { kind, columns }
+    }
+
+    /// A line to print under the input, marking where the error is.
+    pub fn underline(&self) -> String {
+        underline(&self.columns)
+    }
+}
+
+pub(crate) fn underline(columns: &Range<usize>) -> String {
+    let width = (columns.end - columns.start).max(1);
+    format!("{}{}", " ".repeat(columns.start), "^".repeat(width))
+}
+
+pub type Spanned = (usize, Token, usize);
+
+/// Splits an expression into tokens, ignoring any whitespace between them.
+/// Locations are character columns, so that errors line up with the input
+/// when it's printed.
+pub struct Lexer<'a> {
+    input: &'a str,
+    chars: Peekable<Enumerate<CharIndices<'a>>>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Lexer<'a> {
+        Lexer {
+            input,
+            chars: input.char_indices().enumerate().peekable(),
+        }
+    }
+
+    // The column and byte offset just past the end of a run of digits
+    fn digits_end(&mut self, column: usize, offset: usize) -> (usize, usize) {
+        let mut end = (column + 1, offset + 1);
+        while let Some((column, (offset, _))) = self.chars.next_if(|(_, (_, c))| c.is_ascii_digit())
+        {
+            end = (column + 1, offset + 1);
+        }
+        end
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Spanned, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self
+            .chars
+            .next_if(|(_, (_, c))| c.is_whitespace())
+            .is_some()
+        {}
+        let (start, (offset, c)) = self.chars.next()?;
+        let single = |token| Ok((start, token, start + 1));
+        let spanned = match c {
+            '(' => single(Token::Open),
+            ')' => single(Token::Close),
+            '0'..='9' => {
+                let (end, end_offset) = self.digits_end(start, offset);
+                let digits = &self.input[offset..end_offset];
+                match digits.parse() {
+                    Ok(n) => Ok((start, Token::Number(n), end)),
+                    Err(_) => Err(SyntaxError::new(
+                        SyntaxErrorKind::InvalidNumber(digits.to_string()),
+                        start..end,
+                    )),
+                }
+            }
+            _ => match BinOp::from_symbol(c) {
+                Some(op) => single(Token::Operator(op)),
+                None => Err(SyntaxError::new(
+                    SyntaxErrorKind::UnexpectedChar(c),
+                    start..start + 1,
+                )),
+            },
+        };
+        Some(spanned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whitespace() {
+        let tokens: Vec<Token> = Lexer::new(" 12+(3 *\t45 )")
+            .map(|res| res.unwrap().1)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Number(12),
+                Token::Operator(BinOp::Add),
+                Token::Open,
+                Token::Number(3),
+                Token::Operator(BinOp::Mul),
+                Token::Number(45),
+                Token::Close,
+            ]
+        );
+        assert_eq!(Lexer::new("   ").next(), None);
+    }
+
+    #[test]
+    fn spans() {
+        // Columns count the wide space as one, though it's three bytes
+        let spans: Vec<Spanned> = Lexer::new("\u{3000}123 -4")
+            .map(|res| res.unwrap())
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (1, Token::Number(123), 4),
+                (5, Token::Operator(BinOp::Sub), 6),
+                (6, Token::Number(4), 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let err = Lexer::new("1 + x").nth(2).unwrap().unwrap_err();
+        assert_eq!(err.kind, SyntaxErrorKind::UnexpectedChar('x'));
+        assert_eq!(err.to_string(), "Column 5: Unexpected character: x");
+        assert_eq!(err.underline(), "    ^");
+
+        let err = Lexer::new("2 * 99999999999999999999")
+            .find_map(|res| res.err())
+            .unwrap();
+        assert_eq!(err.columns, 4..24);
+        assert_eq!(err.underline(), format!("    {}", "^".repeat(20)));
+    }
+}
@@ -1,4 +1,10 @@
 use lalrpop_util::lalrpop_mod;
 
-lalrpop_mod!(pub grammar);
+lalrpop_mod!(#[allow(clippy::all)] pub grammar);
+#[cfg(feature = "bigint")]
+pub mod big;
+pub mod lexer;
+pub mod parse;
+pub mod pratt;
+pub mod precedence;
 pub mod types;
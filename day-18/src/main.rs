@@ -1,21 +1,52 @@
 use commons::io::load_file_lines;
-use day_18::grammar;
+use day_18::parse;
+use day_18::precedence::Precedence;
+use day_18::types::Expr;
+use std::env;
+
+fn explain(name: &str, expr: &Expr, precedence: &Precedence) {
+    println!("  {}:", name);
+    println!("    grouping: {}", expr);
+    println!("    rpn:      {}", expr.to_rpn());
+    for step in expr.fold_steps() {
+        match step {
+            Ok(step) => println!("    = {}", step.minimal(precedence)),
+            Err(e) => println!("    {}", e),
+        }
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+fn total(exprs: impl Iterator<Item = Expr>) -> i64 {
+    exprs.fold(0, |total, expr| {
+        let value = expr.evaluate().unwrap();
+        total.checked_add(value).expect("Total overflowed")
+    })
+}
+
+#[cfg(feature = "bigint")]
+fn total(exprs: impl Iterator<Item = Expr>) -> num_bigint::BigInt {
+    exprs.map(|expr| expr.evaluate_big().unwrap()).sum()
+}
 
 fn main() {
-    let ltr_parser = grammar::LtrExpressionParser::new();
-    let infix_parser = grammar::InfixExpressionParser::new();
     let input: Vec<String> = load_file_lines::<String>("input.txt")
         .map(|res| res.unwrap())
         .collect();
 
-    let part1: i64 = input
-        .iter()
-        .map(|i| ltr_parser.parse(i).unwrap().evaluate())
-        .sum();
+    // Show how each line was read before giving the answers
+    if env::args().any(|arg| arg == "--explain") {
+        for line in &input {
+            println!("{}", line);
+            let ltr = parse::left_to_right(line).unwrap();
+            explain("left to right", &ltr, &Precedence::left_to_right());
+            let infix = parse::addition_first(line).unwrap();
+            explain("addition first", &infix, &Precedence::addition_first());
+        }
+    }
+
+    let part1 = total(input.iter().map(|i| parse::left_to_right(i).unwrap()));
     println!("{}", part1);
-    let part2: i64 = input
-        .iter()
-        .map(|i| infix_parser.parse(i).unwrap().evaluate())
-        .sum();
+    let part2 = total(input.iter().map(|i| parse::addition_first(i).unwrap()));
     println!("{}", part2);
 }
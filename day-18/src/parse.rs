@@ -0,0 +1,85 @@
+//! Entry points for the grammar's two parsers, taking care of lexing and
+//! turning LALRPOP's errors into `SyntaxError`s.
+
+use crate::grammar::{InfixExpressionParser, LtrExpressionParser};
+use crate::lexer::{Lexer, SyntaxError, SyntaxErrorKind, Token};
+use crate::types::Expr;
+use lalrpop_util::ParseError;
+
+fn syntax_error(input: &str, err: ParseError<usize, Token, SyntaxError>) -> SyntaxError {
+    match err {
+        // The lexer reports bad characters itself, so this shouldn't happen
+        ParseError::InvalidToken { location } => match input.chars().nth(location) {
+            Some(c) => SyntaxError::new(SyntaxErrorKind::UnexpectedChar(c), location..location + 1),
+            None => SyntaxError::new(SyntaxErrorKind::UnexpectedEnd, location..location),
+        },
+        ParseError::UnrecognizedEOF { location, .. } => {
+            SyntaxError::new(SyntaxErrorKind::UnexpectedEnd, location..location)
+        }
+        ParseError::UnrecognizedToken {
+            token: (start, token, end),
+            ..
+        }
+        | ParseError::ExtraToken {
+            token: (start, token, end),
+        } => SyntaxError::new(SyntaxErrorKind::UnexpectedToken(token), start..end),
+        ParseError::User { error } => error,
+    }
+}
+
+/// Part 1: `+` and `*` bind equally, grouping from the left.
+pub fn left_to_right(input: &str) -> Result<Expr, SyntaxError> {
+    LtrExpressionParser::new()
+        .parse(Lexer::new(input))
+        .map_err(|err| syntax_error(input, err))
+}
+
+/// Part 2: `+` binds tighter than `*`.
+pub fn addition_first(input: &str) -> Result<Expr, SyntaxError> {
+    InfixExpressionParser::new()
+        .parse(Lexer::new(input))
+        .map_err(|err| syntax_error(input, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BinOp;
+
+    #[test]
+    fn whitespace() {
+        let expected = left_to_right("1 + (2 * 3)").unwrap();
+        assert_eq!(left_to_right("1+(2*3)"), Ok(expected.clone()));
+        assert_eq!(left_to_right("  1 +\t( 2  *3 ) "), Ok(expected));
+        assert_eq!(addition_first("2*3+4").unwrap().evaluate(), Ok(14));
+    }
+
+    #[test]
+    fn errors() {
+        let err = left_to_right("1 + (2 * 3").unwrap_err();
+        assert_eq!(err.kind, SyntaxErrorKind::UnexpectedEnd);
+        assert_eq!(err.to_string(), "Column 11: Unexpected end of expression");
+
+        let err = addition_first("1 + * 3").unwrap_err();
+        assert_eq!(
+            err.kind,
+            SyntaxErrorKind::UnexpectedToken(Token::Operator(BinOp::Mul))
+        );
+        assert_eq!(err.columns, 4..5);
+
+        // The grammar doesn't know about subtraction
+        let err = left_to_right("3 - 1").unwrap_err();
+        assert_eq!(err.to_string(), "Column 3: Unexpected operator: -");
+
+        let err = addition_first("(1 + 2) 3").unwrap_err();
+        assert_eq!(err.to_string(), "Column 9: Unexpected number: 3");
+
+        let err = syntax_error("1 + 2 = 3", ParseError::InvalidToken { location: 6 });
+        assert_eq!(err.kind, SyntaxErrorKind::UnexpectedChar('='));
+        assert_eq!(err.columns, 6..7);
+
+        let err = left_to_right("1 + 2 = 3").unwrap_err();
+        assert_eq!(err.kind, SyntaxErrorKind::UnexpectedChar('='));
+        assert_eq!(err.underline(), "      ^");
+    }
+}
@@ -0,0 +1,288 @@
+use crate::lexer::{self, Lexer, Spanned, SyntaxError, SyntaxErrorKind, Token};
+use crate::precedence::{Associativity, Precedence};
+use crate::types::{BinOp, Expr};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::vec;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PrattError {
+    #[error(transparent)]
+    Syntax(#[from] SyntaxError),
+    /// The input is fine, but the precedence table doesn't say how the
+    /// operator binds.
+    #[error("Column {}: Operator not in precedence table: {op}", .columns.start + 1)]
+    UnknownOperator { op: BinOp, columns: Range<usize> },
+}
+
+impl PrattError {
+    /// 0-based, counting characters rather than bytes.
+    pub fn columns(&self) -> &Range<usize> {
+        match self {
+            PrattError::Syntax(e) => &e.columns,
+            PrattError::UnknownOperator { columns, .. } => columns,
+        }
+    }
+
+    /// A line to print under the input, marking where the error is.
+    pub fn underline(&self) -> String {
+        lexer::underline(self.columns())
+    }
+}
+
+struct Tokens {
+    tokens: Peekable<vec::IntoIter<Spanned>>,
+    /// The column just past the end of the input.
+    end: usize,
+}
+
+impl Tokens {
+    fn peek(&mut self) -> Option<Spanned> {
+        self.tokens.peek().copied()
+    }
+
+    fn next(&mut self) -> Result<Spanned, SyntaxError> {
+        let end = self.end;
+        self.tokens
+            .next()
+            .ok_or_else(|| SyntaxError::new(SyntaxErrorKind::UnexpectedEnd, end..end))
+    }
+}
+
+fn unexpected((start, token, end): Spanned) -> SyntaxError {
+    SyntaxError::new(SyntaxErrorKind::UnexpectedToken(token), start..end)
+}
+
+/// A precedence climbing parser, for reading expressions under any
+/// precedence table rather than just the two the grammar knows.
+pub struct PrattParser {
+    precedence: Precedence,
+}
+
+impl PrattParser {
+    pub fn new(precedence: Precedence) -> PrattParser {
+        PrattParser { precedence }
+    }
+
+    pub fn precedence(&self) -> &Precedence {
+        &self.precedence
+    }
+
+    pub fn parse(&self, input: &str) -> Result<Expr, PrattError> {
+        let mut tokens = Tokens {
+            tokens: Lexer::new(input)
+                .collect::<Result<Vec<Spanned>, _>>()?
+                .into_iter()
+                .peekable(),
+            end: input.chars().count(),
+        };
+        let expr = self.expression(&mut tokens, 0)?;
+        match tokens.peek() {
+            None => Ok(expr),
+            Some(spanned) => Err(unexpected(spanned).into()),
+        }
+    }
+
+    // Parses operators binding at least as tightly as `min_level`
+    fn expression(&self, tokens: &mut Tokens, min_level: u16) -> Result<Expr, PrattError> {
+        let mut lhs = self.operand(tokens)?;
+        while let Some((start, Token::Operator(op), end)) = tokens.peek() {
+            let (level, associativity) =
+                self.precedence
+                    .binary(op)
+                    .ok_or(PrattError::UnknownOperator {
+                        op,
+                        columns: start..end,
+                    })?;
+            let level = u16::from(level);
+            if level < min_level {
+                break;
+            }
+            tokens.next()?;
+            // Left associative operators stop the right hand side at the
+            // next operator of the same level, so it's grouped on the left
+            let rhs_level = match associativity {
+                Associativity::Left => level + 1,
+                Associativity::Right => level,
+            };
+            let rhs = self.expression(tokens, rhs_level)?;
+            lhs = Expr::binary(op, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn operand(&self, tokens: &mut Tokens) -> Result<Expr, PrattError> {
+        let spanned = tokens.next()?;
+        match spanned.1 {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Operator(BinOp::Sub) => {
+                let level = self.precedence.negation().into();
+                Ok(Expr::Neg(Box::new(self.expression(tokens, level)?)))
+            }
+            Token::Open => {
+                let expr = self.expression(tokens, 0)?;
+                match tokens.next()? {
+                    (_, Token::Close, _) => Ok(expr),
+                    spanned => Err(unexpected(spanned).into()),
+                }
+            }
+            _ => Err(unexpected(spanned).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const EXAMPLES: [(&str, i64, i64); 6] = [
+        ("1 + 2 * 3 + 4 * 5 + 6", 71, 231),
+        ("1 + (2 * 3) + (4 * (5 + 6))", 51, 51),
+        ("2 * 3 + (4 * 5)", 26, 46),
+        ("5 + (8 * 3 + 9 + 3 * 4 * 3)", 437, 1445),
+        ("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))", 12240, 669060),
+        (
+            "((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2",
+            13632,
+            23340,
+        ),
+    ];
+
+    #[test]
+    fn presets_match_grammar() {
+        let ltr = PrattParser::new(Precedence::left_to_right());
+        let infix = PrattParser::new(Precedence::addition_first());
+        for (line, part1, part2) in &EXAMPLES {
+            let expr = ltr.parse(line).unwrap();
+            assert_eq!(Ok(&expr), parse::left_to_right(line).as_ref());
+            assert_eq!(expr.evaluate(), Ok(*part1));
+
+            // The grammar groups `+` and `*` from the right, which only
+            // changes the tree, not the answer
+            let expr = infix.parse(line).unwrap();
+            let grammar = parse::addition_first(line).unwrap();
+            assert_eq!(expr.evaluate(), Ok(*part2));
+            assert_eq!(grammar.evaluate(), Ok(*part2));
+        }
+    }
+
+    #[test]
+    fn levels_share_grouping() {
+        let infix = PrattParser::new(Precedence::addition_first());
+        let expr = infix.parse("8 * 2 / 4").unwrap();
+        assert_eq!(expr.to_string(), "((8 * 2) / 4)");
+        assert_eq!(expr.evaluate(), Ok(4));
+        assert_eq!(infix.parse("8 / 4 * 2").unwrap().evaluate(), Ok(4));
+        assert_eq!(infix.parse("10 - 2 + 3").unwrap().evaluate(), Ok(11));
+        assert_eq!(infix.parse("10 + 2 - 3").unwrap().evaluate(), Ok(9));
+        assert_eq!(infix.parse("1 + 2 * 6 / 3 - 1").unwrap().evaluate(), Ok(9));
+
+        let table = infix.precedence();
+        for expr in &[
+            Expr::binary(
+                BinOp::Div,
+                Expr::binary(BinOp::Mul, Expr::Number(8), Expr::Number(2)),
+                Expr::Number(4),
+            ),
+            Expr::binary(
+                BinOp::Mul,
+                Expr::Number(8),
+                Expr::binary(BinOp::Div, Expr::Number(2), Expr::Number(4)),
+            ),
+            Expr::binary(
+                BinOp::Add,
+                Expr::binary(BinOp::Sub, Expr::Number(10), Expr::Number(2)),
+                Expr::Number(3),
+            ),
+            Expr::binary(
+                BinOp::Sub,
+                Expr::Number(10),
+                Expr::binary(BinOp::Add, Expr::Number(2), Expr::Number(3)),
+            ),
+        ] {
+            let printed = expr.minimal(table).to_string();
+            assert_eq!(infix.parse(&printed).as_ref(), Ok(expr));
+        }
+    }
+
+    #[test]
+    fn extra_operators() {
+        let standard = PrattParser::new(Precedence::standard());
+        let eval = |s: &str| standard.parse(s).unwrap().evaluate().unwrap();
+        assert_eq!(eval("1 + 2 * 3"), 7);
+        assert_eq!(eval("10 - 4 - 3"), 3);
+        assert_eq!(eval("100 / 10 / 5"), 2);
+        assert_eq!(eval("17 % 5 * 2"), 4);
+        assert_eq!(eval("2 ^ 3 ^ 2"), 512);
+        assert_eq!(eval("-2 ^ 2"), -4);
+        assert_eq!(eval("--3 - -3"), 6);
+        assert_eq!(eval("-(1 + 2) * 3"), -9);
+
+        let ltr = PrattParser::new(Precedence::left_to_right());
+        assert_eq!(ltr.parse("2 ^ 3 ^ 2").unwrap().evaluate(), Ok(64));
+        assert_eq!(ltr.parse("-2 ^ 2").unwrap().evaluate(), Ok(4));
+    }
+
+    #[test]
+    fn custom_table() {
+        // Subtraction first, then everything else right to left
+        let table = Precedence::new()
+            .with(BinOp::Sub, 2, Associativity::Left)
+            .with(BinOp::Add, 1, Associativity::Right)
+            .with(BinOp::Mul, 1, Associativity::Right);
+        let parser = PrattParser::new(table);
+        let expr = parser.parse("2 * 3 + 4 - 1").unwrap();
+        assert_eq!(expr.to_string(), "(2 * (3 + (4 - 1)))");
+        assert_eq!(
+            expr.minimal(parser.precedence()).to_string(),
+            "2 * 3 + 4 - 1"
+        );
+        let err = parser.parse("2 / 3").unwrap_err();
+        assert_eq!(
+            err,
+            PrattError::UnknownOperator {
+                op: BinOp::Div,
+                columns: 2..3
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Column 3: Operator not in precedence table: /"
+        );
+        assert_eq!(err.underline(), "  ^");
+    }
+
+    #[test]
+    fn errors() {
+        let parser = PrattParser::new(Precedence::standard());
+        let kind = |s: &str| match parser.parse(s).unwrap_err() {
+            PrattError::Syntax(e) => e.kind,
+            e => panic!("Not a syntax error: {}", e),
+        };
+        assert_eq!(kind(""), SyntaxErrorKind::UnexpectedEnd);
+        assert_eq!(
+            kind("1 2"),
+            SyntaxErrorKind::UnexpectedToken(Token::Number(2))
+        );
+        assert_eq!(
+            kind("1 * * 2"),
+            SyntaxErrorKind::UnexpectedToken(Token::Operator(BinOp::Mul))
+        );
+
+        let err = parser.parse("(1 + 2").unwrap_err();
+        assert_eq!(err.to_string(), "Column 7: Unexpected end of expression");
+        let err = parser.parse("1 + 2)").unwrap_err();
+        assert_eq!(err.to_string(), "Column 6: Unexpected closing bracket");
+        let err = parser.parse("1 & 2").unwrap_err();
+        assert_eq!(err.underline(), "  ^");
+        assert_eq!(
+            parser
+                .parse("1+99999999999999999999")
+                .unwrap_err()
+                .to_string(),
+            "Column 3: Number out of range: 99999999999999999999"
+        );
+    }
+}
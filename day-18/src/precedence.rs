@@ -0,0 +1,88 @@
+use crate::types::BinOp;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+/// How tightly each operator binds, with higher levels binding tighter.
+/// Operators sharing a level should share an associativity too, otherwise
+/// `a - b + c` and `a + b - c` group differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Precedence {
+    binary: HashMap<BinOp, (u8, Associativity)>,
+    negation: u8,
+}
+
+impl Default for Precedence {
+    fn default() -> Self {
+        Precedence {
+            binary: HashMap::new(),
+            negation: u8::MAX,
+        }
+    }
+}
+
+impl Precedence {
+    /// A table with no binary operators, and unary minus binding tightest.
+    pub fn new() -> Precedence {
+        Default::default()
+    }
+
+    pub fn set(&mut self, op: BinOp, level: u8, associativity: Associativity) -> &mut Self {
+        self.binary.insert(op, (level, associativity));
+        self
+    }
+
+    /// As `set`, for building up a table in one expression.
+    pub fn with(mut self, op: BinOp, level: u8, associativity: Associativity) -> Self {
+        self.set(op, level, associativity);
+        self
+    }
+
+    /// Sets how tightly unary minus binds. Below `^`, `-2 ^ 2` is `-(2 ^ 2)`.
+    pub fn with_negation(mut self, level: u8) -> Self {
+        self.negation = level;
+        self
+    }
+
+    pub fn binary(&self, op: BinOp) -> Option<(u8, Associativity)> {
+        self.binary.get(&op).copied()
+    }
+
+    pub fn negation(&self) -> u8 {
+        self.negation
+    }
+
+    /// Part 1: every operator binds equally, grouping from the left.
+    pub fn left_to_right() -> Precedence {
+        BinOp::ALL.iter().fold(Precedence::new(), |table, op| {
+            table.with(*op, 1, Associativity::Left)
+        })
+    }
+
+    /// Part 2: `+` and `-` bind tighter than `*`, `/` and `%`.
+    pub fn addition_first() -> Precedence {
+        Precedence::new()
+            .with(BinOp::Add, 2, Associativity::Left)
+            .with(BinOp::Sub, 2, Associativity::Left)
+            .with(BinOp::Mul, 1, Associativity::Left)
+            .with(BinOp::Div, 1, Associativity::Left)
+            .with(BinOp::Rem, 1, Associativity::Left)
+            .with(BinOp::Pow, 3, Associativity::Right)
+    }
+
+    /// The usual rules from school.
+    pub fn standard() -> Precedence {
+        Precedence::new()
+            .with(BinOp::Add, 1, Associativity::Left)
+            .with(BinOp::Sub, 1, Associativity::Left)
+            .with(BinOp::Mul, 2, Associativity::Left)
+            .with(BinOp::Div, 2, Associativity::Left)
+            .with(BinOp::Rem, 2, Associativity::Left)
+            .with(BinOp::Pow, 4, Associativity::Right)
+            .with_negation(3)
+    }
+}
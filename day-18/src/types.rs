@@ -1,16 +1,354 @@
-#[derive(Debug)]
+use crate::precedence::{Associativity, Precedence};
+use std::convert::TryFrom;
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalErrorKind {
+    #[error("Overflow")]
+    Overflow,
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("Negative exponent")]
+    NegativeExponent,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("{kind} in {expr}")]
+pub struct EvalError {
+    pub kind: EvalErrorKind,
+    /// The innermost sub-expression that couldn't be evaluated.
+    pub expr: Expr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+impl BinOp {
+    pub const ALL: [BinOp; 6] = [
+        BinOp::Add,
+        BinOp::Sub,
+        BinOp::Mul,
+        BinOp::Div,
+        BinOp::Rem,
+        BinOp::Pow,
+    ];
+
+    pub fn symbol(self) -> char {
+        match self {
+            BinOp::Add => '+',
+            BinOp::Sub => '-',
+            BinOp::Mul => '*',
+            BinOp::Div => '/',
+            BinOp::Rem => '%',
+            BinOp::Pow => '^',
+        }
+    }
+
+    pub fn from_symbol(c: char) -> Option<BinOp> {
+        BinOp::ALL.iter().copied().find(|op| op.symbol() == c)
+    }
+
+    /// Division and remainder truncate towards zero, as `i64` does.
+    pub fn apply(self, a: i64, b: i64) -> Result<i64, EvalErrorKind> {
+        let result = match self {
+            BinOp::Add => a.checked_add(b),
+            BinOp::Sub => a.checked_sub(b),
+            BinOp::Mul => a.checked_mul(b),
+            BinOp::Div | BinOp::Rem if b == 0 => return Err(EvalErrorKind::DivisionByZero),
+            BinOp::Div => a.checked_div(b),
+            BinOp::Rem => a.checked_rem(b),
+            BinOp::Pow if b < 0 => return Err(EvalErrorKind::NegativeExponent),
+            BinOp::Pow => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+        };
+        result.ok_or(EvalErrorKind::Overflow)
+    }
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Expr {
     Number(i64),
-    Add(Box<Expr>, Box<Expr>),
-    Mul(Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
 }
 
 impl Expr {
-    pub fn evaluate(&self) -> i64 {
+    pub fn binary(op: BinOp, a: Expr, b: Expr) -> Expr {
+        Expr::Binary(op, Box::new(a), Box::new(b))
+    }
+
+    pub fn evaluate(&self) -> Result<i64, EvalError> {
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Neg(a) => a.evaluate()?.checked_neg().ok_or_else(|| EvalError {
+                kind: EvalErrorKind::Overflow,
+                expr: self.clone(),
+            }),
+            Expr::Binary(op, a, b) => {
+                op.apply(a.evaluate()?, b.evaluate()?)
+                    .map_err(|kind| EvalError {
+                        kind,
+                        expr: self.clone(),
+                    })
+            }
+        }
+    }
+
+    /// Prints with only the brackets needed to parse back to the same tree
+    /// under `precedence`.
+    pub fn minimal<'a>(&'a self, precedence: &'a Precedence) -> Minimal<'a> {
+        Minimal {
+            expr: self,
+            precedence,
+        }
+    }
+
+    /// Reverse Polish notation, e.g. `1 2 + 3 *` for `(1 + 2) * 3`. Negation
+    /// is written `neg`.
+    pub fn to_rpn(&self) -> String {
+        match self {
+            Expr::Number(n) => n.to_string(),
+            Expr::Neg(a) => format!("{} neg", a.to_rpn()),
+            Expr::Binary(op, a, b) => format!("{} {} {}", a.to_rpn(), b.to_rpn(), op),
+        }
+    }
+
+    /// Collapses every operation whose operands are all numbers, leaving
+    /// anything else for later steps. Folding a number does nothing.
+    pub fn fold_step(&self) -> Result<Expr, EvalError> {
+        let folded = match self {
+            Expr::Number(n) => Expr::Number(*n),
+            Expr::Neg(a) => match **a {
+                Expr::Number(_) => Expr::Number(self.evaluate()?),
+                _ => Expr::Neg(Box::new(a.fold_step()?)),
+            },
+            Expr::Binary(op, a, b) => match (&**a, &**b) {
+                (Expr::Number(_), Expr::Number(_)) => Expr::Number(self.evaluate()?),
+                _ => Expr::binary(*op, a.fold_step()?, b.fold_step()?),
+            },
+        };
+        Ok(folded)
+    }
+
+    /// Every step of folding down to a single number, starting with the
+    /// expression itself. Stops after the first error.
+    pub fn fold_steps(&self) -> impl Iterator<Item = Result<Expr, EvalError>> {
+        std::iter::successors(Some(Ok(self.clone())), |step| match step {
+            Ok(Expr::Number(_)) | Err(_) => None,
+            Ok(e) => Some(e.fold_step()),
+        })
+    }
+
+    /// Folds all the way down to a single number.
+    pub fn fold(&self) -> Result<Expr, EvalError> {
+        self.evaluate().map(Expr::Number)
+    }
+}
+
+/// Fully bracketed, so the grouping is clear whatever the precedence.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Expr::Number(n) => *n,
-            Expr::Add(a, b) => a.evaluate() + b.evaluate(),
-            Expr::Mul(a, b) => a.evaluate() * b.evaluate(),
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Neg(a) => write!(f, "(-{})", a),
+            Expr::Binary(op, a, b) => write!(f, "({} {} {})", a, op, b),
+        }
+    }
+}
+
+pub struct Minimal<'a> {
+    expr: &'a Expr,
+    precedence: &'a Precedence,
+}
+
+impl<'a> Minimal<'a> {
+    // Higher binds tighter. Operators missing from the table get the lowest
+    // level, so they're always bracketed.
+    fn level(&self, expr: &Expr) -> u16 {
+        match expr {
+            Expr::Number(n) if *n >= 0 => u16::MAX,
+            Expr::Number(_) | Expr::Neg(_) => self.precedence.negation().into(),
+            Expr::Binary(op, _, _) => self
+                .precedence
+                .binary(*op)
+                .map_or(0, |(level, _)| level.into()),
         }
     }
+
+    fn fmt_child(&self, f: &mut fmt::Formatter<'_>, child: &Expr, bracket: bool) -> fmt::Result {
+        let inner = child.minimal(self.precedence);
+        if bracket {
+            write!(f, "({})", inner)
+        } else {
+            write!(f, "{}", inner)
+        }
+    }
+}
+
+impl<'a> fmt::Display for Minimal<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = self.level(self.expr);
+        match self.expr {
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Neg(a) => {
+                write!(f, "-")?;
+                self.fmt_child(f, a, self.level(a) < level)
+            }
+            Expr::Binary(op, a, b) => {
+                // A child at the same level only needs brackets on the side
+                // the operator doesn't group towards
+                let right = self.precedence.binary(*op).map(|(_, assoc)| assoc)
+                    == Some(Associativity::Right);
+                let (a_level, b_level) = (self.level(a), self.level(b));
+                self.fmt_child(f, a, a_level < level || (a_level == level && right))?;
+                write!(f, " {} ", op)?;
+                self.fmt_child(f, b, b_level < level || (b_level == level && !right))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{addition_first, left_to_right};
+    use crate::pratt::PrattParser;
+
+    const EXAMPLE: &str = "1 + (2 * 3) + (4 * (5 + 6))";
+
+    #[test]
+    fn display() {
+        let ltr_table = Precedence::left_to_right();
+        let infix_table = Precedence::addition_first();
+        let ltr = left_to_right(EXAMPLE).unwrap();
+        assert_eq!(ltr.to_string(), "((1 + (2 * 3)) + (4 * (5 + 6)))");
+        assert_eq!(
+            ltr.minimal(&ltr_table).to_string(),
+            "1 + (2 * 3) + (4 * (5 + 6))"
+        );
+
+        let infix = addition_first("2 * 3 + (4 * 5)").unwrap();
+        assert_eq!(infix.to_string(), "(2 * (3 + (4 * 5)))");
+        assert_eq!(infix.minimal(&infix_table).to_string(), "2 * 3 + (4 * 5)");
+        assert_eq!(infix.minimal(&ltr_table).to_string(), "2 * (3 + (4 * 5))");
+
+        let neg = Expr::binary(
+            BinOp::Pow,
+            Expr::Neg(Box::new(Expr::Number(2))),
+            Expr::Number(2),
+        );
+        assert_eq!(neg.to_string(), "((-2) ^ 2)");
+        assert_eq!(neg.minimal(&infix_table).to_string(), "-2 ^ 2");
+        assert_eq!(neg.minimal(&Precedence::standard()).to_string(), "(-2) ^ 2");
+    }
+
+    #[test]
+    fn minimal_round_trips() {
+        let lines = [
+            EXAMPLE,
+            "2 * 3 + (4 * 5)",
+            "5 + (8 * 3 + 9 + 3 * 4 * 3)",
+            "((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2",
+        ];
+        let ltr_table = Precedence::left_to_right();
+        let infix_table = Precedence::addition_first();
+        let infix_parser = PrattParser::new(infix_table.clone());
+        for line in &lines {
+            let ltr = left_to_right(line).unwrap();
+            let printed = ltr.minimal(&ltr_table).to_string();
+            assert_eq!(left_to_right(&printed), Ok(ltr));
+
+            // The grammar groups from the right, unlike the table, so read
+            // the printed form back with the table instead
+            let infix = addition_first(line).unwrap();
+            let printed = infix.minimal(&infix_table).to_string();
+            assert_eq!(infix_parser.parse(&printed), Ok(infix));
+        }
+    }
+
+    #[test]
+    fn rpn() {
+        let ltr = left_to_right(EXAMPLE).unwrap();
+        assert_eq!(ltr.to_rpn(), "1 2 3 * + 4 5 6 + * +");
+        assert_eq!(Expr::Number(7).to_rpn(), "7");
+        assert_eq!(Expr::Neg(Box::new(Expr::Number(7))).to_rpn(), "7 neg");
+    }
+
+    #[test]
+    fn folding() {
+        let table = Precedence::left_to_right();
+        let ltr = left_to_right(EXAMPLE).unwrap();
+        let steps: Vec<String> = ltr
+            .fold_steps()
+            .map(|e| e.unwrap().minimal(&table).to_string())
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                "1 + (2 * 3) + (4 * (5 + 6))",
+                "1 + 6 + (4 * 11)",
+                "7 + 44",
+                "51"
+            ]
+        );
+        assert_eq!(ltr.fold(), Ok(Expr::Number(51)));
+
+        let steps: Vec<_> = left_to_right("(1 + 2) * (3 * 0)")
+            .unwrap()
+            .fold_steps()
+            .collect();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[2], Ok(Expr::Number(0)));
+    }
+
+    #[test]
+    fn overflow() {
+        let expr = left_to_right("1 + (3037000500 * 3037000500 + 2) * 2").unwrap();
+        let err = expr.evaluate().unwrap_err();
+        assert_eq!(err.kind, EvalErrorKind::Overflow);
+        assert_eq!(err.to_string(), "Overflow in (3037000500 * 3037000500)");
+        assert_eq!(expr.fold(), Err(err));
+        assert!(expr.fold_steps().last().unwrap().is_err());
+
+        let max = left_to_right("9223372036854775807").unwrap();
+        assert_eq!(max.evaluate(), Ok(i64::MAX));
+        assert_eq!(
+            Expr::Neg(Box::new(Expr::Number(i64::MIN))).evaluate(),
+            Err(EvalError {
+                kind: EvalErrorKind::Overflow,
+                expr: Expr::Neg(Box::new(Expr::Number(i64::MIN))),
+            })
+        );
+    }
+
+    #[test]
+    fn arithmetic_errors() {
+        let div = Expr::binary(BinOp::Div, Expr::Number(1), Expr::Number(0));
+        let err = Expr::binary(BinOp::Add, Expr::Number(1), div.clone())
+            .evaluate()
+            .unwrap_err();
+        assert_eq!(err.kind, EvalErrorKind::DivisionByZero);
+        assert_eq!(err.expr, div);
+        assert_eq!(BinOp::Rem.apply(1, 0), Err(EvalErrorKind::DivisionByZero));
+        assert_eq!(BinOp::Div.apply(i64::MIN, -1), Err(EvalErrorKind::Overflow));
+        assert_eq!(
+            BinOp::Pow.apply(2, -1),
+            Err(EvalErrorKind::NegativeExponent)
+        );
+        assert_eq!(BinOp::Pow.apply(2, 62), Ok(1 << 62));
+        assert_eq!(BinOp::Pow.apply(2, 63), Err(EvalErrorKind::Overflow));
+    }
 }
@@ -0,0 +1,331 @@
+use crate::types::MatchType;
+use commons::search::dijkstra;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CompileError {
+    #[error("Rule {0} is not defined")]
+    UndefinedRule(u32),
+    #[error("Rule {0} is recursive")]
+    Recursive(u32),
+    #[error("Rule {0} can recurse without consuming anything")]
+    UnboundedRecursion(u32),
+    #[error(transparent)]
+    Regex(#[from] regex::Error),
+}
+
+type Ruleset = HashMap<u32, MatchType>;
+
+// Each alternative of a rule, as the sequence of things to match in order
+fn alternatives(matcher: &MatchType) -> Vec<Vec<Item<'_>>> {
+    match matcher {
+        MatchType::References(ids) => vec![ids.iter().map(|id| Item::Rule(*id)).collect()],
+        MatchType::Or(a, b) => {
+            let mut alts = alternatives(a);
+            alts.extend(alternatives(b));
+            alts
+        }
+        MatchType::Chars(s) => vec![vec![Item::Chars(s)]],
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Item<'a> {
+    Rule(u32),
+    Chars(&'a str),
+}
+
+fn rule(ruleset: &Ruleset, id: u32) -> Result<&MatchType, CompileError> {
+    ruleset.get(&id).ok_or(CompileError::UndefinedRule(id))
+}
+
+/// The length of the shortest message each rule can match. Rules that can't
+/// match anything at all, like `8: 8`, are left out.
+fn min_lengths(ruleset: &Ruleset) -> Result<HashMap<u32, usize>, CompileError> {
+    let mut lengths: HashMap<u32, usize> = HashMap::new();
+    // Shortening one rule can shorten the ones using it, so go until nothing
+    // changes
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (id, matcher) in ruleset {
+            for alt in alternatives(matcher) {
+                let mut total = Some(0);
+                for item in alt {
+                    let len = match item {
+                        Item::Chars(s) => Some(s.chars().count()),
+                        Item::Rule(r) => {
+                            rule(ruleset, r)?;
+                            lengths.get(&r).copied()
+                        }
+                    };
+                    total = total.and_then(|t| len.map(|l| t + l));
+                }
+                if let Some(total) = total {
+                    if lengths.get(id).is_none_or(|l| total < *l) {
+                        lengths.insert(*id, total);
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+    Ok(lengths)
+}
+
+/// How many times each rule can be nested inside itself in a match of at
+/// most `max_len` characters. Rules which can't be nested are absent.
+fn nesting_limits(ruleset: &Ruleset, max_len: usize) -> Result<HashMap<u32, usize>, CompileError> {
+    let lengths = min_lengths(ruleset)?;
+    // Going from a rule to one it references means also matching everything
+    // else in that alternative, so that's the cost of the step
+    let successors = |node: &Option<u32>| -> Vec<(Option<u32>, usize)> {
+        let id = match node {
+            Some(id) => *id,
+            None => return Vec::new(),
+        };
+        let mut next = Vec::new();
+        for alt in alternatives(&ruleset[&id]) {
+            let item_len = |item: &Item<'_>| match item {
+                Item::Chars(s) => Some(s.chars().count()),
+                Item::Rule(r) => lengths.get(r).copied(),
+            };
+            let lens: Option<Vec<usize>> = alt.iter().map(item_len).collect();
+            let lens = match lens {
+                Some(lens) => lens,
+                None => continue,
+            };
+            let total: usize = lens.iter().sum();
+            for (item, len) in alt.iter().zip(&lens) {
+                if let Item::Rule(r) = item {
+                    next.push((Some(*r), total - len));
+                }
+            }
+        }
+        next
+    };
+
+    let mut ids: Vec<&u32> = ruleset.keys().collect();
+    ids.sort_unstable();
+    let mut limits = HashMap::new();
+    for id in ids {
+        let min_len = match lengths.get(id) {
+            Some(len) => *len,
+            None => continue,
+        };
+        // Search from a stand-in for the rule, so the route back to it isn't
+        // found straight away
+        let start_successors = |node: &Option<u32>| match node {
+            None => successors(&Some(*id)),
+            _ => successors(node),
+        };
+        let cycle = dijkstra(&None, start_successors, |node| *node == Some(*id));
+        if let Some((_, gain)) = cycle {
+            if gain == 0 {
+                return Err(CompileError::UnboundedRecursion(*id));
+            }
+            // Each time round adds at least `gain` characters to the
+            // shortest match
+            let limit = max_len.saturating_sub(min_len) / gain + 1;
+            limits.insert(*id, limit);
+        }
+    }
+    Ok(limits)
+}
+
+struct Expander<'a> {
+    ruleset: &'a Ruleset,
+    /// None when recursion isn't allowed at all.
+    limits: Option<HashMap<u32, usize>>,
+    /// How many times each rule is currently being expanded.
+    active: HashMap<u32, usize>,
+    /// Patterns for rules which didn't run into a nesting limit, so are the
+    /// same wherever they're used.
+    done: HashMap<u32, Option<String>>,
+}
+
+impl<'a> Expander<'a> {
+    // The pattern for a rule, or None if it can't match anything within the
+    // nesting limits. The flag is whether a limit was reached.
+    fn expand(&mut self, id: u32) -> Result<(Option<String>, bool), CompileError> {
+        if let Some(pattern) = self.done.get(&id) {
+            return Ok((pattern.clone(), false));
+        }
+        let active = self.active.get(&id).copied().unwrap_or(0);
+        match &self.limits {
+            None if active > 0 => return Err(CompileError::Recursive(id)),
+            Some(limits) if active >= limits.get(&id).copied().unwrap_or(1) => {
+                return Ok((None, true))
+            }
+            _ => {}
+        }
+
+        *self.active.entry(id).or_insert(0) += 1;
+        let mut limited = false;
+        let mut patterns = Vec::new();
+        for alt in alternatives(rule(self.ruleset, id)?) {
+            let mut pattern = Some(String::new());
+            for item in alt {
+                let part = match item {
+                    Item::Chars(s) => Some(regex::escape(s)),
+                    Item::Rule(r) => {
+                        let (part, hit_limit) = self.expand(r)?;
+                        limited |= hit_limit;
+                        part
+                    }
+                };
+                pattern = pattern.and_then(|p| part.map(|part| p + &part));
+            }
+            patterns.extend(pattern);
+        }
+        *self.active.get_mut(&id).unwrap() -= 1;
+
+        let pattern = match patterns.len() {
+            0 => None,
+            1 => patterns.pop(),
+            _ => Some(format!("(?:{})", patterns.join("|"))),
+        };
+        if !limited {
+            self.done.insert(id, pattern.clone());
+        }
+        Ok((pattern, limited))
+    }
+}
+
+/// A rule compiled down to a regular expression, so matching doesn't need to
+/// walk the rule set at all.
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    regex: Option<Regex>,
+}
+
+impl CompiledRule {
+    /// Compiles rule `root`, which can't be recursive as a regular expression
+    /// can't match it exactly.
+    pub fn compile(ruleset: &Ruleset, root: u32) -> Result<CompiledRule, CompileError> {
+        CompiledRule::build(ruleset, root, None)
+    }
+
+    /// Compiles rule `root`, unrolling any recursive rules as many times as
+    /// could be needed to match a message of up to `max_len` characters.
+    /// Longer messages might not match when they should.
+    pub fn compile_bounded(
+        ruleset: &Ruleset,
+        root: u32,
+        max_len: usize,
+    ) -> Result<CompiledRule, CompileError> {
+        let limits = nesting_limits(ruleset, max_len)?;
+        CompiledRule::build(ruleset, root, Some(limits))
+    }
+
+    fn build(
+        ruleset: &Ruleset,
+        root: u32,
+        limits: Option<HashMap<u32, usize>>,
+    ) -> Result<CompiledRule, CompileError> {
+        let mut expander = Expander {
+            ruleset,
+            limits,
+            active: HashMap::new(),
+            done: HashMap::new(),
+        };
+        let regex = match expander.expand(root)?.0 {
+            Some(pattern) => Some(
+                RegexBuilder::new(&format!("^{}$", pattern))
+                    // Unrolled rules get big quickly
+                    .size_limit(1 << 28)
+                    .build()?,
+            ),
+            None => None,
+        };
+        Ok(CompiledRule { regex })
+    }
+
+    pub fn matches_exact(&self, s: &str) -> bool {
+        self.regex.as_ref().is_some_and(|regex| regex.is_match(s))
+    }
+
+    /// The regular expression, or None if the rule can't match anything.
+    pub fn pattern(&self) -> Option<&str> {
+        self.regex.as_ref().map(|regex| regex.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{ruleset, EXAMPLE};
+
+    #[test]
+    fn compile() {
+        let rules = ruleset(EXAMPLE);
+        let compiled = CompiledRule::compile(&rules, 0).unwrap();
+        for (message, expected) in &[
+            ("ababbb", true),
+            ("abbbab", true),
+            ("bababa", false),
+            ("aaabbb", false),
+            ("aaaabbb", false),
+        ] {
+            assert_eq!(compiled.matches_exact(message), *expected);
+            assert_eq!(rules[&0].matches_exact(&rules, message), *expected);
+        }
+        assert_eq!(
+            CompiledRule::compile(&rules, 3).unwrap().pattern(),
+            Some("^(?:ab|ba)$")
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let rules = ruleset("0: 1 2\n1: \"a\"");
+        assert!(matches!(
+            CompiledRule::compile(&rules, 0),
+            Err(CompileError::UndefinedRule(2))
+        ));
+        let rules = ruleset("0: 1 | 1 0\n1: \"a\"");
+        assert!(matches!(
+            CompiledRule::compile(&rules, 0),
+            Err(CompileError::Recursive(0))
+        ));
+        let rules = ruleset("0: 1 | 2\n1: \"a\"\n2: 0");
+        assert!(matches!(
+            CompiledRule::compile_bounded(&rules, 0, 10),
+            Err(CompileError::UnboundedRecursion(0))
+        ));
+        let rules = ruleset("0: 1 0\n1: \"a\"");
+        let compiled = CompiledRule::compile_bounded(&rules, 0, 10).unwrap();
+        assert_eq!(compiled.pattern(), None);
+        assert!(!compiled.matches_exact("aaa"));
+    }
+
+    #[test]
+    fn limits() {
+        let rules = ruleset(
+            "0: 8 11\n8: 42 | 42 8\n11: 42 31 | 42 11 31\n42: 1 1\n31: 2\n1: \"a\"\n2: \"b\"",
+        );
+        let limits = nesting_limits(&rules, 12).unwrap();
+        // 8 is at least 2 long and grows by 2, 11 is at least 3 and grows by 3
+        assert_eq!(limits.get(&8), Some(&6));
+        assert_eq!(limits.get(&11), Some(&4));
+        assert_eq!(limits.get(&0), None);
+
+        let compiled = CompiledRule::compile_bounded(&rules, 0, 12).unwrap();
+        assert!(compiled.matches_exact("aaaaaab"));
+        assert!(compiled.matches_exact("aaaaaaaabb"));
+        assert!(!compiled.matches_exact("aaaabb"));
+        assert!(compiled.matches_exact(&format!("{}b", "a".repeat(14))));
+        // Needs 8 nested seven times, which can't happen in 12 characters
+        assert!(!compiled.matches_exact(&format!("{}b", "a".repeat(16))));
+    }
+
+    #[test]
+    fn left_recursion() {
+        let rules = ruleset("0: 1 | 0 1\n1: \"a\" | \"b\"");
+        let compiled = CompiledRule::compile_bounded(&rules, 0, 5).unwrap();
+        assert!(compiled.matches_exact("abbab"));
+        assert!(!compiled.matches_exact(""));
+    }
+}
@@ -0,0 +1,415 @@
+//! A general context free recogniser, using Earley's algorithm. Unlike
+//! `MatchType::matches` or compiling to a regex, it copes with any rules at
+//! all, including left recursion and rules that match nothing.
+
+use crate::types::MatchType;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum EarleyError {
+    #[error("Rule {0} is not defined")]
+    UndefinedRule(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Symbol {
+    Rule(u32),
+    Chars(Vec<char>),
+}
+
+fn alternatives(matcher: &MatchType, into: &mut Vec<Vec<Symbol>>) {
+    match matcher {
+        MatchType::References(ids) => into.push(ids.iter().map(|id| Symbol::Rule(*id)).collect()),
+        MatchType::Or(a, b) => {
+            alternatives(a, into);
+            alternatives(b, into);
+        }
+        MatchType::Chars(s) => into.push(vec![Symbol::Chars(s.chars().collect())]),
+    }
+}
+
+/// A partly matched alternative: `dot` symbols of it match the message from
+/// `origin` up to the set the item is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Item {
+    rule: u32,
+    alt: usize,
+    dot: usize,
+    origin: usize,
+}
+
+impl Item {
+    fn advanced(self) -> Item {
+        Item {
+            dot: self.dot + 1,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ItemSet {
+    items: Vec<Item>,
+    seen: HashSet<Item>,
+}
+
+impl ItemSet {
+    fn add(&mut self, item: Item) {
+        if self.seen.insert(item) {
+            self.items.push(item);
+        }
+    }
+
+    fn contains(&self, item: &Item) -> bool {
+        self.seen.contains(item)
+    }
+}
+
+/// How a rule matched part of a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTree {
+    Rule { id: u32, children: Vec<ParseTree> },
+    Chars(String),
+}
+
+impl ParseTree {
+    /// The part of the message the tree covers.
+    pub fn text(&self) -> String {
+        match self {
+            ParseTree::Rule { children, .. } => children.iter().map(|c| c.text()).collect(),
+            ParseTree::Chars(s) => s.clone(),
+        }
+    }
+}
+
+/// As nested lists, e.g. `(0 (4 "a") (5 "b"))`.
+impl fmt::Display for ParseTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseTree::Rule { id, children } => {
+                write!(f, "({}", id)?;
+                for child in children {
+                    write!(f, " {}", child)?;
+                }
+                write!(f, ")")
+            }
+            ParseTree::Chars(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+// The item sets for a message, kept so a parse tree can be read back out
+struct Chart<'a> {
+    recogniser: &'a Recogniser,
+    message: Vec<char>,
+    sets: Vec<ItemSet>,
+}
+
+pub struct Recogniser {
+    alternatives: HashMap<u32, Vec<Vec<Symbol>>>,
+    nullable: HashSet<u32>,
+}
+
+impl Recogniser {
+    pub fn new(ruleset: &HashMap<u32, MatchType>) -> Result<Recogniser, EarleyError> {
+        let mut alts = HashMap::new();
+        for (id, matcher) in ruleset {
+            let mut rule_alts = Vec::new();
+            alternatives(matcher, &mut rule_alts);
+            for symbol in rule_alts.iter().flatten() {
+                if let Symbol::Rule(r) = symbol {
+                    if !ruleset.contains_key(r) {
+                        return Err(EarleyError::UndefinedRule(*r));
+                    }
+                }
+            }
+            alts.insert(*id, rule_alts);
+        }
+
+        let mut recogniser = Recogniser {
+            alternatives: alts,
+            nullable: HashSet::new(),
+        };
+        // A rule can match nothing if any alternative is all things that can
+        // match nothing, which can depend on rules found later
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (id, rule_alts) in &recogniser.alternatives {
+                if !recogniser.nullable.contains(id)
+                    && rule_alts
+                        .iter()
+                        .any(|alt| alt.iter().all(|s| recogniser.is_nullable(s)))
+                {
+                    recogniser.nullable.insert(*id);
+                    changed = true;
+                }
+            }
+        }
+        Ok(recogniser)
+    }
+
+    fn is_nullable(&self, symbol: &Symbol) -> bool {
+        match symbol {
+            Symbol::Rule(id) => self.nullable.contains(id),
+            Symbol::Chars(chars) => chars.is_empty(),
+        }
+    }
+
+    fn symbols(&self, item: &Item) -> &[Symbol] {
+        &self.alternatives[&item.rule][item.alt]
+    }
+
+    fn chart(&self, root: u32, message: &str) -> Option<Chart<'_>> {
+        let message: Vec<char> = message.chars().collect();
+        let mut sets: Vec<ItemSet> = (0..=message.len()).map(|_| ItemSet::default()).collect();
+        for alt in 0..self.alternatives.get(&root)?.len() {
+            sets[0].add(Item {
+                rule: root,
+                alt,
+                dot: 0,
+                origin: 0,
+            });
+        }
+
+        for k in 0..=message.len() {
+            // Items get added to the set as it's worked through
+            let mut i = 0;
+            while i < sets[k].items.len() {
+                let item = sets[k].items[i];
+                i += 1;
+                match self.symbols(&item).get(item.dot) {
+                    Some(Symbol::Rule(r)) => {
+                        for alt in 0..self.alternatives[r].len() {
+                            sets[k].add(Item {
+                                rule: *r,
+                                alt,
+                                dot: 0,
+                                origin: k,
+                            });
+                        }
+                        // Anything completing in this set has already been
+                        // through, so don't wait for the rule to do so
+                        if self.nullable.contains(r) {
+                            sets[k].add(item.advanced());
+                        }
+                    }
+                    Some(Symbol::Chars(chars)) => {
+                        let end = k + chars.len();
+                        if message.get(k..end) == Some(chars.as_slice()) {
+                            sets[end].add(item.advanced());
+                        }
+                    }
+                    None => {
+                        let waiting: Vec<Item> = sets[item.origin]
+                            .items
+                            .iter()
+                            .filter(|w| {
+                                self.symbols(w).get(w.dot) == Some(&Symbol::Rule(item.rule))
+                            })
+                            .copied()
+                            .collect();
+                        for w in waiting {
+                            sets[k].add(w.advanced());
+                        }
+                    }
+                }
+            }
+        }
+        Some(Chart {
+            recogniser: self,
+            message,
+            sets,
+        })
+    }
+
+    /// Whether rule `root` matches the whole of `message`. Undefined roots
+    /// match nothing.
+    pub fn matches_exact(&self, root: u32, message: &str) -> bool {
+        self.chart(root, message)
+            .is_some_and(|chart| chart.completed(root, 0, chart.message.len()).is_some())
+    }
+
+    /// How rule `root` matches the whole of `message`, if it does. Where
+    /// there's more than one way, this is one of them.
+    pub fn parse(&self, root: u32, message: &str) -> Option<ParseTree> {
+        let chart = self.chart(root, message)?;
+        chart.tree(root, 0, chart.message.len(), &mut HashSet::new())
+    }
+}
+
+impl<'a> Chart<'a> {
+    // The alternatives of `rule` matching from `start` to `end`
+    fn completed(&self, rule: u32, start: usize, end: usize) -> Option<Vec<usize>> {
+        let alts: Vec<usize> = (0..self.recogniser.alternatives[&rule].len())
+            .filter(|alt| {
+                let len = self.recogniser.alternatives[&rule][*alt].len();
+                self.sets[end].contains(&Item {
+                    rule,
+                    alt: *alt,
+                    dot: len,
+                    origin: start,
+                })
+            })
+            .collect();
+        if alts.is_empty() {
+            None
+        } else {
+            Some(alts)
+        }
+    }
+
+    // `active` holds the rules being built, so rules that can match the same
+    // span as themselves don't go round forever
+    fn tree(
+        &self,
+        rule: u32,
+        start: usize,
+        end: usize,
+        active: &mut HashSet<(u32, usize, usize)>,
+    ) -> Option<ParseTree> {
+        if !active.insert((rule, start, end)) {
+            return None;
+        }
+        let tree = self.completed(rule, start, end).and_then(|alts| {
+            alts.into_iter().find_map(|alt| {
+                let item = Item {
+                    rule,
+                    alt,
+                    dot: self.recogniser.alternatives[&rule][alt].len(),
+                    origin: start,
+                };
+                let children = self.children(item, end, active)?;
+                Some(ParseTree::Rule { id: rule, children })
+            })
+        });
+        active.remove(&(rule, start, end));
+        tree
+    }
+
+    // The trees for the symbols before the dot in `item`, which ends at
+    // `end`
+    fn children(
+        &self,
+        item: Item,
+        end: usize,
+        active: &mut HashSet<(u32, usize, usize)>,
+    ) -> Option<Vec<ParseTree>> {
+        if item.dot == 0 {
+            return if end == item.origin {
+                Some(Vec::new())
+            } else {
+                None
+            };
+        }
+        let before = Item {
+            dot: item.dot - 1,
+            ..item
+        };
+        match &self.recogniser.symbols(&item)[item.dot - 1] {
+            Symbol::Chars(chars) => {
+                let mid = end.checked_sub(chars.len())?;
+                if !self.sets[mid].contains(&before) {
+                    return None;
+                }
+                let mut children = self.children(before, mid, active)?;
+                children.push(ParseTree::Chars(chars.iter().collect()));
+                Some(children)
+            }
+            Symbol::Rule(r) => (item.origin..=end).rev().find_map(|mid| {
+                if !self.sets[mid].contains(&before) {
+                    return None;
+                }
+                let child = self.tree(*r, mid, end, active)?;
+                let mut children = self.children(before, mid, active)?;
+                children.push(child);
+                Some(children)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{ruleset, EXAMPLE};
+
+    fn recogniser(rules: &str) -> Recogniser {
+        Recogniser::new(&ruleset(rules)).unwrap()
+    }
+
+    #[test]
+    fn example() {
+        let rules = recogniser(EXAMPLE);
+        for (message, expected) in &[
+            ("ababbb", true),
+            ("abbbab", true),
+            ("bababa", false),
+            ("aaabbb", false),
+            ("aaaabbb", false),
+            ("", false),
+        ] {
+            assert_eq!(rules.matches_exact(0, message), *expected);
+        }
+        let tree = rules.parse(0, "ababbb").unwrap();
+        assert_eq!(
+            tree.to_string(),
+            "(0 (4 \"a\") (1 (3 (5 \"b\") (4 \"a\")) (2 (5 \"b\") (5 \"b\"))) (5 \"b\"))"
+        );
+        assert_eq!(tree.text(), "ababbb");
+        assert_eq!(rules.parse(0, "bababa"), None);
+        assert!(!rules.matches_exact(7, "a"));
+    }
+
+    #[test]
+    fn recursion() {
+        let rules = recogniser(
+            "0: 8 11\n8: 42 | 42 8\n11: 42 31 | 42 11 31\n42: \"a\"\n31: \"b\"\n\
+             1: 2 | 1 2\n2: \"a\" | \"b\"",
+        );
+        assert!(rules.matches_exact(0, "aaaaaabbb"));
+        assert!(rules.matches_exact(0, &format!("{}{}", "a".repeat(60), "b".repeat(30))));
+        assert!(!rules.matches_exact(0, "aaabbb"));
+        assert_eq!(
+            rules.parse(0, "aab").unwrap().to_string(),
+            "(0 (8 (42 \"a\")) (11 (42 \"a\") (31 \"b\")))"
+        );
+
+        // Left recursive
+        assert!(rules.matches_exact(1, "abbab"));
+        assert_eq!(
+            rules.parse(1, "ab").unwrap().to_string(),
+            "(1 (1 (2 \"a\")) (2 \"b\"))"
+        );
+    }
+
+    #[test]
+    fn empty_matches() {
+        // Rules matching nothing, and ones matching themselves
+        let mut ruleset = HashMap::new();
+        ruleset.insert(0, MatchType::References(vec![1, 2, 1]));
+        ruleset.insert(
+            1,
+            MatchType::Or(
+                Box::new(MatchType::Chars(String::new())),
+                Box::new(MatchType::References(vec![1])),
+            ),
+        );
+        ruleset.insert(2, MatchType::Chars("ab".to_string()));
+        let rules = Recogniser::new(&ruleset).unwrap();
+        assert!(rules.matches_exact(0, "ab"));
+        assert!(!rules.matches_exact(0, "a"));
+        assert_eq!(
+            rules.parse(0, "ab").unwrap().to_string(),
+            "(0 (1 \"\") (2 \"ab\") (1 \"\"))"
+        );
+
+        ruleset.insert(3, MatchType::References(vec![4]));
+        assert_eq!(
+            Recogniser::new(&ruleset).err(),
+            Some(EarleyError::UndefinedRule(4))
+        );
+    }
+}
@@ -1,4 +1,9 @@
 use lalrpop_util::lalrpop_mod;
 
-lalrpop_mod!(pub grammar);
+lalrpop_mod!(#[allow(clippy::all)] pub grammar);
+pub mod compile;
+pub mod earley;
+#[cfg(test)]
+pub(crate) mod test_util;
 pub mod types;
+pub mod validate;
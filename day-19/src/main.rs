@@ -1,6 +1,27 @@
 use commons::io::load_file_lines;
+use day_19::compile::CompiledRule;
+use day_19::earley::Recogniser;
+use day_19::validate::validate;
 use day_19::{grammar, types::MatchType};
 use std::collections::HashMap;
+use std::env;
+use std::process;
+
+// Matching a broken rule set would just quietly give the wrong count, but
+// rules that are never used don't matter
+fn check(ruleset: &HashMap<u32, MatchType>) {
+    let problems = validate(ruleset, 0);
+    for problem in &problems {
+        if problem.is_fatal() {
+            eprintln!("Error: {}", problem);
+        } else {
+            eprintln!("Warning: {}", problem);
+        }
+    }
+    if problems.iter().any(|p| p.is_fatal()) {
+        process::exit(1);
+    }
+}
 
 fn main() {
     let parser = grammar::RuleParser::new();
@@ -21,11 +42,11 @@ fn main() {
         }
     }
 
-    let rule = ruleset.get(&0).unwrap();
+    check(&ruleset);
+    let rule = CompiledRule::compile(&ruleset, 0).unwrap();
     let part1 = test_lines
         .iter()
-        .map(|line| rule.matches_exact(&ruleset, line))
-        .filter(|x| *x)
+        .filter(|line| rule.matches_exact(line))
         .count();
     println!("{}", part1);
 
@@ -34,11 +55,34 @@ fn main() {
     let new_11 = parser.parse("11: 42 31 | 42 11 31").unwrap();
     ruleset_2.insert(8, new_8.matcher);
     ruleset_2.insert(11, new_11.matcher);
+    check(&ruleset_2);
 
+    // The new rules loop, so only unroll them as far as the longest message
+    // could need
+    let max_len = test_lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    let rule = CompiledRule::compile_bounded(&ruleset_2, 0, max_len).unwrap();
     let part2 = test_lines
         .iter()
-        .map(|line| rule.matches_exact(&ruleset_2, line))
-        .filter(|x| *x)
+        .filter(|line| rule.matches_exact(line))
         .count();
     println!("{}", part2);
+
+    // Check both answers with the general recogniser, which doesn't need to
+    // know anything about the shape of the rules
+    if env::args().any(|arg| arg == "--earley") {
+        for (ruleset, expected) in &[(&ruleset, part1), (&ruleset_2, part2)] {
+            let recogniser = Recogniser::new(ruleset).unwrap();
+            let count = test_lines
+                .iter()
+                .filter(|line| recogniser.matches_exact(0, line))
+                .count();
+            assert_eq!(count, *expected);
+        }
+        // Show how the first matching message matches
+        let recogniser = Recogniser::new(&ruleset_2).unwrap();
+        let example = test_lines.iter().find_map(|line| recogniser.parse(0, line));
+        if let Some(tree) = example {
+            println!("{}", tree);
+        }
+    }
 }
@@ -0,0 +1,25 @@
+//! Fixtures shared between the test modules.
+
+use crate::grammar::RuleParser;
+use crate::types::MatchType;
+use std::collections::HashMap;
+
+/// The first example from the puzzle.
+pub const EXAMPLE: &str = "0: 4 1 5
+1: 2 3 | 3 2
+2: 4 4 | 5 5
+3: 4 5 | 5 4
+4: \"a\"
+5: \"b\"";
+
+/// Parses one rule per line.
+pub fn ruleset(rules: &str) -> HashMap<u32, MatchType> {
+    let parser = RuleParser::new();
+    rules
+        .lines()
+        .map(|line| {
+            let rule = parser.parse(line).unwrap();
+            (rule.id, rule.matcher)
+        })
+        .collect()
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::ptr;
 
 #[derive(Debug, Clone)]
 pub enum MatchType {
@@ -8,42 +9,58 @@ pub enum MatchType {
 }
 
 impl MatchType {
+    /// The remainders of `s` left after each way this can match the start of
+    /// it. References to undefined rules never match. Neither does a rule
+    /// reached again without using up any of `s`, counting this one, so left
+    /// recursive rules only match through their other alternatives;
+    /// `validate` reports both.
     pub fn matches<'a>(&self, ruleset: &HashMap<u32, MatchType>, s: &'a str) -> Vec<&'a str> {
+        self.matches_from(ruleset, s, &mut vec![(self, s.len())])
+    }
+
+    // `active` holds each rule being matched, along with how much of the
+    // message was left when it started. Rules are compared by address, so
+    // the one `matches` was called on is caught too when it's in `ruleset`.
+    fn matches_from<'r, 'a>(
+        &'r self,
+        ruleset: &'r HashMap<u32, MatchType>,
+        s: &'a str,
+        active: &mut Vec<(&'r MatchType, usize)>,
+    ) -> Vec<&'a str> {
         if s.is_empty() {
             return Vec::new();
         }
 
         match self {
             MatchType::References(l) => {
-                let mut next = Vec::new();
-                next.push(s);
+                let mut next = vec![s];
 
                 for id in l {
-                    let rule = ruleset.get(&id).unwrap();
-                    let next_next = next
-                        .iter()
-                        .map(|remain| rule.matches(ruleset, remain))
-                        .filter(|v| !v.is_empty())
-                        .flatten()
-                        .collect();
+                    let rule = match ruleset.get(id) {
+                        Some(rule) => rule,
+                        None => return Vec::new(),
+                    };
+                    let mut next_next = Vec::new();
+                    for remain in next {
+                        let len = remain.len();
+                        if active.iter().any(|(r, l)| ptr::eq(*r, rule) && *l == len) {
+                            continue;
+                        }
+                        active.push((rule, len));
+                        next_next.extend(rule.matches_from(ruleset, remain, active));
+                        active.pop();
+                    }
                     next = next_next;
                 }
 
                 next
             }
-            MatchType::Or(a, b) => a
-                .matches(ruleset, s)
-                .iter()
-                .chain(b.matches(ruleset, s).iter())
-                .copied()
-                .collect(),
-            MatchType::Chars(a) => {
-                if &s[0..a.len()] == a {
-                    vec![&s[a.len()..]]
-                } else {
-                    Vec::new()
-                }
+            MatchType::Or(a, b) => {
+                let mut remains = a.matches_from(ruleset, s, active);
+                remains.extend(b.matches_from(ruleset, s, active));
+                remains
             }
+            MatchType::Chars(a) => s.strip_prefix(a.as_str()).into_iter().collect(),
         }
     }
 
@@ -63,3 +80,34 @@ impl Rule {
         Rule { id, matcher }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::ruleset;
+
+    #[test]
+    fn matches_without_panicking() {
+        let rules = ruleset("0: 1 2\n1: 3 | 4\n3: \"a\"");
+        // Undefined rules don't match, but the other alternative still can
+        assert!(!rules[&0].matches_exact(&rules, "ab"));
+        assert_eq!(rules[&1].matches(&rules, "ab"), vec!["b"]);
+
+        let rules = ruleset("0: 1 1\n1: \"a\"");
+        assert!(!rules[&0].matches_exact(&rules, "a"));
+        assert!(!rules[&0].matches_exact(&rules, "é"));
+        assert!(rules[&0].matches_exact(&rules, "aa"));
+    }
+
+    #[test]
+    fn left_recursion() {
+        let rules = ruleset("0: 1 | 0 1\n1: \"a\" | \"b\"\n2: 2 1");
+        // Cut off at the first level, so only the other alternative matches
+        assert!(rules[&0].matches_exact(&rules, "a"));
+        assert!(!rules[&0].matches_exact(&rules, "ab"));
+        assert!(!rules[&0].matches_exact(&rules, "abb"));
+        assert!(rules[&2].matches(&rules, "ab").is_empty());
+        // Right recursion still works
+        let rules = ruleset("0: 1 | 1 0\n1: \"a\" | \"b\"");
+        assert!(rules[&0].matches_exact(&rules, "abba"));
+    }
+}
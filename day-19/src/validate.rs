@@ -0,0 +1,291 @@
+use crate::types::MatchType;
+use commons::search::reachable;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    #[error("Rule {0} is not defined, so nothing can match")]
+    MissingRoot(u32),
+    #[error("Rule {rule} refers to rule {reference}, which is not defined")]
+    UndefinedRule { rule: u32, reference: u32 },
+    #[error("Rule {0} can't be reached")]
+    Unreachable(u32),
+    /// Listed from a rule round and back to it again.
+    #[error("Left recursion: {}", .0.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" -> "))]
+    LeftRecursion(Vec<u32>),
+    #[error("Rule {0} has an empty literal")]
+    EmptyLiteral(u32),
+}
+
+impl Problem {
+    /// Whether the problem can change what matches. Unreachable rules are
+    /// just never used.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, Problem::Unreachable(_))
+    }
+}
+
+type Ruleset = HashMap<u32, MatchType>;
+
+fn references(matcher: &MatchType) -> Vec<u32> {
+    match matcher {
+        MatchType::References(ids) => ids.clone(),
+        MatchType::Or(a, b) => {
+            let mut ids = references(a);
+            ids.extend(references(b));
+            ids
+        }
+        MatchType::Chars(_) => Vec::new(),
+    }
+}
+
+fn has_empty_literal(matcher: &MatchType) -> bool {
+    match matcher {
+        MatchType::References(_) => false,
+        MatchType::Or(a, b) => has_empty_literal(a) || has_empty_literal(b),
+        MatchType::Chars(s) => s.is_empty(),
+    }
+}
+
+// Whether a rule can match without using up any of the message
+fn nullable(ruleset: &Ruleset) -> HashSet<u32> {
+    fn can_be_empty(matcher: &MatchType, nullable: &HashSet<u32>) -> bool {
+        match matcher {
+            MatchType::References(ids) => ids.iter().all(|id| nullable.contains(id)),
+            MatchType::Or(a, b) => can_be_empty(a, nullable) || can_be_empty(b, nullable),
+            MatchType::Chars(s) => s.is_empty(),
+        }
+    }
+
+    let mut nullable = HashSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (id, matcher) in ruleset {
+            if !nullable.contains(id) && can_be_empty(matcher, &nullable) {
+                nullable.insert(*id);
+                changed = true;
+            }
+        }
+    }
+    nullable
+}
+
+// The rules that can be tried at the same point in the message as `matcher`
+// itself: the first in each sequence, and any after ones that can be empty
+fn left_corners(matcher: &MatchType, nullable: &HashSet<u32>) -> Vec<u32> {
+    match matcher {
+        MatchType::References(ids) => {
+            let mut corners = Vec::new();
+            for id in ids {
+                corners.push(*id);
+                if !nullable.contains(id) {
+                    break;
+                }
+            }
+            corners
+        }
+        MatchType::Or(a, b) => {
+            let mut corners = left_corners(a, nullable);
+            corners.extend(left_corners(b, nullable));
+            corners
+        }
+        MatchType::Chars(_) => Vec::new(),
+    }
+}
+
+fn left_recursion(ruleset: &Ruleset) -> Vec<Vec<u32>> {
+    let nullable = nullable(ruleset);
+    let corners: HashMap<u32, Vec<u32>> = ruleset
+        .iter()
+        .map(|(id, matcher)| {
+            let mut next = left_corners(matcher, &nullable);
+            next.retain(|n| ruleset.contains_key(n));
+            next.sort_unstable();
+            next.dedup();
+            (*id, next)
+        })
+        .collect();
+
+    let mut ids: Vec<u32> = ruleset.keys().copied().collect();
+    ids.sort_unstable();
+    let mut done = HashSet::new();
+    let mut cycles = Vec::new();
+    for root in ids {
+        if done.contains(&root) {
+            continue;
+        }
+        // Each stack entry is a rule and its corners yet to be visited
+        let mut stack = vec![(root, corners[&root].clone())];
+        let mut on_stack = HashSet::new();
+        on_stack.insert(root);
+        while let Some((id, pending)) = stack.last_mut() {
+            let id = *id;
+            match pending.pop() {
+                Some(next) if on_stack.contains(&next) => {
+                    let start = stack.iter().position(|(n, _)| *n == next).unwrap();
+                    let mut cycle: Vec<u32> = stack[start..].iter().map(|(n, _)| *n).collect();
+                    // Start from the lowest rule, so the same cycle found from
+                    // different places looks the same
+                    let lowest = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap();
+                    cycle.rotate_left(lowest);
+                    cycle.push(cycle[0]);
+                    if !cycles.contains(&cycle) {
+                        cycles.push(cycle);
+                    }
+                }
+                Some(next) if !done.contains(&next) => {
+                    on_stack.insert(next);
+                    stack.push((next, corners[&next].clone()));
+                }
+                Some(_) => {}
+                None => {
+                    on_stack.remove(&id);
+                    done.insert(id);
+                    stack.pop();
+                }
+            }
+        }
+    }
+    cycles.sort();
+    cycles
+}
+
+/// Everything wrong with a rule set that would stop matching from `root`
+/// working. Left recursion is reported as at least one cycle through each
+/// loop, and would send `MatchType::matches` round forever.
+pub fn validate(ruleset: &Ruleset, root: u32) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if !ruleset.contains_key(&root) {
+        problems.push(Problem::MissingRoot(root));
+    }
+
+    let mut ids: Vec<u32> = ruleset.keys().copied().collect();
+    ids.sort_unstable();
+    for id in &ids {
+        let mut refs = references(&ruleset[id]);
+        refs.sort_unstable();
+        refs.dedup();
+        problems.extend(
+            refs.into_iter()
+                .filter(|r| !ruleset.contains_key(r))
+                .map(|reference| Problem::UndefinedRule {
+                    rule: *id,
+                    reference,
+                }),
+        );
+    }
+
+    let used = reachable(&root, |id| {
+        ruleset
+            .get(id)
+            .map(references)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|r| ruleset.contains_key(r))
+    });
+    problems.extend(
+        ids.iter()
+            .filter(|id| **id != root && !used.contains(id))
+            .map(|id| Problem::Unreachable(*id)),
+    );
+
+    problems.extend(
+        left_recursion(ruleset)
+            .into_iter()
+            .map(Problem::LeftRecursion),
+    );
+    problems.extend(
+        ids.iter()
+            .filter(|id| has_empty_literal(&ruleset[id]))
+            .map(|id| Problem::EmptyLiteral(*id)),
+    );
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{ruleset, EXAMPLE};
+
+    #[test]
+    fn valid() {
+        assert_eq!(validate(&ruleset(EXAMPLE), 0), vec![]);
+        let part2 = "0: 8 11\n8: 42 | 42 8\n11: 42 31 | 42 11 31\n42: \"a\"\n31: \"b\"";
+        assert_eq!(validate(&ruleset(part2), 0), vec![]);
+    }
+
+    #[test]
+    fn undefined_and_unreachable() {
+        let rules = ruleset("0: 1 2 | 3\n1: \"a\"\n4: 1 5\n5: 4");
+        assert_eq!(
+            validate(&rules, 0),
+            vec![
+                Problem::UndefinedRule {
+                    rule: 0,
+                    reference: 2
+                },
+                Problem::UndefinedRule {
+                    rule: 0,
+                    reference: 3
+                },
+                Problem::Unreachable(4),
+                Problem::Unreachable(5),
+            ]
+        );
+        assert!(Problem::MissingRoot(9).is_fatal());
+        assert!(!Problem::Unreachable(4).is_fatal());
+        assert_eq!(
+            validate(&rules, 9),
+            vec![
+                Problem::MissingRoot(9),
+                Problem::UndefinedRule {
+                    rule: 0,
+                    reference: 2
+                },
+                Problem::UndefinedRule {
+                    rule: 0,
+                    reference: 3
+                },
+                Problem::Unreachable(0),
+                Problem::Unreachable(1),
+                Problem::Unreachable(4),
+                Problem::Unreachable(5),
+            ]
+        );
+    }
+
+    #[test]
+    fn left_recursive() {
+        let rules = ruleset("0: 1 | 0 1\n1: 2 3 | \"a\"\n2: 3 | \"b\"\n3: 1 2 | 2");
+        let problems = validate(&rules, 0);
+        assert_eq!(
+            problems,
+            vec![
+                Problem::LeftRecursion(vec![0, 0]),
+                Problem::LeftRecursion(vec![1, 2, 3, 1]),
+                Problem::LeftRecursion(vec![2, 3, 2]),
+            ]
+        );
+        assert_eq!(problems[1].to_string(), "Left recursion: 1 -> 2 -> 3 -> 1");
+    }
+
+    #[test]
+    fn empty_literals() {
+        let mut rules = ruleset("0: 1 0 | 2\n1: \"a\"\n2: \"b\"");
+        assert_eq!(validate(&rules, 0), vec![]);
+        // Once 1 can match nothing, 0 can get back to itself without moving
+        rules.insert(
+            1,
+            MatchType::Or(
+                Box::new(MatchType::Chars("a".to_string())),
+                Box::new(MatchType::Chars(String::new())),
+            ),
+        );
+        assert_eq!(
+            validate(&rules, 0),
+            vec![Problem::LeftRecursion(vec![0, 0]), Problem::EmptyLiteral(1)]
+        );
+    }
+}
@@ -1,8 +1,7 @@
-#![feature(iterator_fold_self)]
-
+use commons::assign::assign;
 use commons::io::load_file_lines;
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 use thiserror::Error;
 
@@ -58,30 +57,15 @@ fn main() {
         }
     }
 
-    let mut found = HashMap::new();
-    let mut to_find: VecDeque<&String> = allergens.keys().copied().collect();
-    while !to_find.is_empty() {
-        let allergen_name = to_find.pop_front().unwrap();
-        let possible_ingredients: HashSet<String> = allergens
-            .get(allergen_name)
-            .unwrap()
+    let candidates = allergens.iter().map(|(allergen_name, items)| {
+        let possible_ingredients = items
             .iter()
             .map(|item| item.ingredients.clone())
-            .fold_first(|a, c| a.intersection(&c).map(|s| s.to_string()).collect())
-            .unwrap()
-            .iter()
-            .filter(|s| found.values().find(|x| x == s).is_none())
-            .map(|s| s.to_string())
-            .collect();
-        if possible_ingredients.len() == 1 {
-            found.insert(
-                allergen_name,
-                possible_ingredients.iter().next().unwrap().clone(),
-            );
-        } else {
-            to_find.push_back(allergen_name);
-        }
-    }
+            .reduce(|a, c| a.intersection(&c).cloned().collect())
+            .unwrap();
+        (*allergen_name, possible_ingredients)
+    });
+    let found = assign(candidates).unwrap();
 
     let identified_ingredients: HashSet<&String> = found.values().collect();
     let part1: usize = food_items
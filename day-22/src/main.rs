@@ -1,6 +1,7 @@
+use commons::cycle::CycleDetector;
 use commons::io::load_file_records;
 use std::cmp;
-use std::collections::{HashSet, VecDeque};
+use std::collections::VecDeque;
 
 fn simple_game<'a>(
     deck1: impl IntoIterator<Item = &'a usize>,
@@ -39,14 +40,15 @@ fn recursive_game<'a>(
 ) -> (usize, VecDeque<usize>) {
     let mut player1: VecDeque<usize> = deck1.into_iter().copied().collect();
     let mut player2: VecDeque<usize> = deck2.into_iter().copied().collect();
-    let mut seen_games = HashSet::new();
+    let mut seen_games = CycleDetector::new();
 
     while !(player1.is_empty() || player2.is_empty()) {
-        let hand_key = (player1.clone(), player2.clone());
-        if seen_games.contains(&hand_key) {
+        if seen_games
+            .observe((player1.clone(), player2.clone()))
+            .is_some()
+        {
             return (1, player1);
         }
-        seen_games.insert(hand_key);
 
         let c1 = player1.pop_front().unwrap();
         let c2 = player2.pop_front().unwrap();
@@ -1,72 +1,52 @@
+use commons::collections::LinkedRing;
 use commons::io::load_file_lines;
-// I am using it for sorted, but it doesn't know that I am
-#[allow(unused_imports)]
-use itertools::Itertools;
-
-fn game(input: &mut Vec<usize>, first: usize, iterations: usize) {
-    let input_min = input
-        .iter()
-        .enumerate()
-        .filter(|(_, x)| **x != 0)
-        .map(|(i, _)| i)
-        .min()
-        .unwrap();
-    let input_max = input
-        .iter()
-        .enumerate()
-        .filter(|(_, x)| **x != 0)
-        .map(|(i, _)| i)
-        .max()
-        .unwrap();
+use std::env;
 
-    let mut current_cup = first;
-    for _ in 0..iterations {
-        //print_from(input, current_cup);
+/// Lays out the starting labels followed by every label after the highest
+/// of them, up to `cups` cups in total, and plays `moves` moves. The labels
+/// don't need to start at 1 or be consecutive.
+fn play(labels: &[usize], cups: usize, moves: usize) -> LinkedRing {
+    let input_max = *labels.iter().max().unwrap();
+    let mut ring: LinkedRing = labels.iter().copied().chain(input_max + 1..=cups).collect();
+    let min_label = *labels.iter().min().unwrap();
+    let max_label = input_max.max(cups);
 
-        let mut next_three = Vec::with_capacity(3);
-        next_three.push(input[current_cup]);
-        next_three.push(input[next_three[0]]);
-        next_three.push(input[next_three[1]]);
-        let next_cup = input[next_three[2]];
+    let mut current_cup = labels[0];
+    for _ in 0..moves {
+        let picked_up = ring.splice_out(current_cup, 3);
 
-        let mut target_cup = current_cup - 1;
+        let mut target_cup = current_cup;
         loop {
-            if target_cup < input_min {
-                target_cup = input_max;
-            }
-            if next_three.contains(&target_cup) {
-                target_cup -= 1;
+            target_cup = if target_cup == min_label {
+                max_label
             } else {
+                target_cup - 1
+            };
+            // Picked up cups are out of the ring, as are gaps in the labels
+            if ring.contains(target_cup) {
                 break;
             }
         }
 
-        let insertion_end = input[target_cup];
-        input[target_cup] = next_three[0];
-        input[next_three[2]] = insertion_end;
-        input[current_cup] = next_cup;
-
-        current_cup = next_cup;
+        ring.insert_after(target_cup, &picked_up);
+        current_cup = ring.next(current_cup).unwrap();
     }
+    ring
 }
 
-fn part1(mut input: Vec<usize>, first: usize) -> String {
-    game(&mut input, first, 100);
-
-    let mut s = String::new();
-    let mut i = input[1];
-    while i != 1 {
-        s += i.to_string().as_str();
-        i = input[i];
-    }
-    s
+fn part1(labels: &[usize]) -> String {
+    let ring = play(labels, labels.len(), 100);
+    ring.iter_from(1)
+        .skip(1)
+        .map(|cup| cup.to_string())
+        .collect()
 }
 
-fn part2(mut input: Vec<usize>, first: usize) -> u64 {
-    game(&mut input, first, 10000000);
+fn part2(labels: &[usize], cups: usize, moves: usize) -> u64 {
+    let ring = play(labels, cups, moves);
 
-    let first = input[1];
-    let second = input[first];
+    let first = ring.next(1).unwrap();
+    let second = ring.next(first).unwrap();
 
     (first as u64) * (second as u64)
 }
@@ -80,29 +60,53 @@ fn main() {
         .chars()
         .map(|c| c.to_string().parse().unwrap())
         .collect();
-    let input_max = *input.iter().max().unwrap();
-
-    let mut reflist = Vec::new();
-    reflist.resize_with(input_max + 1, Default::default);
-    let mut last = input[0];
-    for &i in input.iter().skip(1) {
-        reflist[last] = i;
-        last = i;
+
+    // Optionally the number of cups and moves for part 2
+    let mut counts = env::args().skip(1).map(|arg| arg.parse().unwrap());
+    let cups = counts.next().unwrap_or(1_000_000);
+    let moves = counts.next().unwrap_or(10_000_000);
+
+    println!("{}", part1(&input));
+    println!("{}", part2(&input, cups, moves));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [usize; 9] = [3, 8, 9, 1, 2, 5, 4, 6, 7];
+
+    #[test]
+    fn example() {
+        let ring = play(&EXAMPLE, EXAMPLE.len(), 10);
+        assert_eq!(
+            ring.iter_from(1).skip(1).collect::<Vec<_>>(),
+            vec![9, 2, 6, 5, 8, 3, 7, 4]
+        );
+        assert_eq!(part1(&EXAMPLE), "67384529");
     }
-    reflist[last] = input[0];
-
-    let part1_result = part1(reflist.clone(), input[0]);
-    println!("{}", part1_result);
-
-    reflist.resize_with(1000001, Default::default);
-    let mut last = input[input.len() - 1];
-    let mut v = input_max + 1;
-    while v <= 1000000 {
-        reflist[last] = v;
-        last = v;
-        v += 1;
+
+    #[test]
+    fn other_labels() {
+        // Only the order of the labels matters, so spreading them out and
+        // moving them up shouldn't change the game
+        let relabel = |cup: usize| cup * 2 + 10;
+        let labels: Vec<usize> = EXAMPLE.iter().map(|&cup| relabel(cup)).collect();
+        let ring = play(&labels, 0, 10);
+        assert_eq!(
+            ring.iter_from(relabel(1)).skip(1).collect::<Vec<_>>(),
+            vec![9, 2, 6, 5, 8, 3, 7, 4]
+                .into_iter()
+                .map(relabel)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn extra_cups() {
+        let ring = play(&EXAMPLE, 20, 0);
+        assert_eq!(ring.len(), 20);
+        assert_eq!(ring.next(7), Some(10));
+        assert_eq!(ring.next(20), Some(3));
     }
-    reflist[1000000] = input[0];
-    let part2_result = part2(reflist, input[0]);
-    println!("{}", part2_result);
 }
@@ -1,7 +1,7 @@
 use commons::geom::Point;
-use commons::grid::{Grid, SparseGrid};
+use commons::grid::{Coordinate, SparseSet};
 use commons::io::load_file_lines;
-use std::collections::{HashSet, VecDeque};
+use std::cmp;
 
 #[derive(Debug)]
 enum HexDir {
@@ -56,71 +56,57 @@ impl HexDir {
     }
 }
 
+// Hex tiles in doubled coordinates: east/west moves two columns, diagonals
+// move one column and one row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Tile(isize, isize);
+
+impl Coordinate for Tile {
+    fn neighbours(&self) -> Vec<Self> {
+        HexDir::offsets()
+            .iter()
+            .map(|offset| Tile(self.0 + offset.0, self.1 + offset.1))
+            .collect()
+    }
+
+    fn component_min(&self, other: &Self) -> Self {
+        Tile(cmp::min(self.0, other.0), cmp::min(self.1, other.1))
+    }
+
+    fn component_max(&self, other: &Self) -> Self {
+        Tile(cmp::max(self.0, other.0), cmp::max(self.1, other.1))
+    }
+}
+
 fn main() {
     let input_strs = load_file_lines::<String>("input.txt").map(|res| res.unwrap());
     let input = input_strs.map(|s| HexDir::parse_line(&s));
-    let mut grid: SparseGrid<bool> = SparseGrid::new();
+    let mut grid: SparseSet<Tile> = SparseSet::new();
 
     for line in input {
         let mut coord = Point::origin();
         for dir in line {
             coord += dir.offset();
         }
-        let coord_tuple = coord.tuple_copy();
-        let current = match grid.at(&coord_tuple) {
-            Some(a) => *a,
-            None => false,
-        };
-        grid.set(coord_tuple, !current);
+        let (x, y) = coord.tuple_copy();
+        grid.toggle(Tile(x, y));
     }
 
-    let part1 = grid.points().iter().filter(|(_, v)| **v).count();
+    let part1 = grid.len();
     println!("{}", part1);
 
     for _ in 0..100 {
-        let mut seen = HashSet::new();
-        let mut q: VecDeque<(isize, isize)> = grid
-            .points()
-            .iter()
-            .map(|(coord, _)| coord)
-            .copied()
-            .collect();
-        let mut next_grid = SparseGrid::new();
-        while !q.is_empty() {
-            let coord = q.pop_front().unwrap();
-            let cell_is_black = grid.at(&coord).copied().unwrap_or(false);
-            let mut black_arround = 0;
-            for offset in HexDir::offsets() {
-                let surround_coord = (coord.0 + offset.0, coord.1 + offset.1);
-                // Queue cells surrounding black cells as they may have changed
-                if cell_is_black && !seen.contains(&surround_coord) {
-                    seen.insert(surround_coord);
-                    q.push_back(surround_coord);
-                }
-                black_arround += match grid.at(&surround_coord) {
-                    Some(true) => 1,
-                    _ => 0,
-                };
+        grid = grid.step(|is_black, black_around| {
+            if is_black {
+                // Currently black, so flip depending on rules
+                !(black_around == 0 || black_around > 2)
+            } else {
+                // Currently white
+                black_around == 2
             }
-
-            let new_value = match grid.at(&coord) {
-                Some(true) => {
-                    // Currently black, so flip depending on rules
-                    !(black_arround == 0 || black_arround > 2)
-                }
-                _ => {
-                    // Currently white
-                    black_arround == 2
-                }
-            };
-
-            if new_value {
-                next_grid.set(coord, true);
-            }
-        }
-        grid = next_grid;
+        });
     }
 
-    let part2 = grid.points().iter().filter(|(_, v)| **v).count();
+    let part2 = grid.len();
     println!("{}", part2);
 }
@@ -1,54 +1,27 @@
-struct Transform {
-    last: u64,
-    subject_number: u64,
-    divisor: u64,
-}
-
-impl Transform {
-    pub fn new(subject_number: u64, divisor: u64) -> Transform {
-        Transform {
-            last: 1,
-            subject_number,
-            divisor,
-        }
-    }
-}
+use commons::io::load_file_lines;
+use commons::math::{discrete_log, mod_pow};
 
-impl Iterator for Transform {
-    type Item = u64;
+const SUBJECT_NUMBER: u64 = 7;
+const DIVISOR: u64 = 20201227;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let next = (self.last * self.subject_number) % self.divisor;
-        self.last = next;
-        Some(next)
-    }
+fn loop_size(public_key: u64) -> u64 {
+    discrete_log(SUBJECT_NUMBER, public_key, DIVISOR).expect("No loop size gives that key")
 }
 
 fn main() {
-    let transform = Transform::new(7, 20201227);
-
-    let card_public = 8184785;
-    let door_public = 5293040;
+    let public_keys: Vec<u64> = load_file_lines("input.txt")
+        .map(|res| res.unwrap())
+        .collect();
+    let card_public = public_keys[0];
+    let door_public = public_keys[1];
 
-    let mut other_public = 0;
-    let mut found_private = 0;
+    let card_loop = loop_size(card_public);
+    let shared_secret = mod_pow(door_public, card_loop, DIVISOR).unwrap();
 
-    for (loop_num, key) in transform.enumerate() {
-        if key == card_public {
-            found_private = loop_num + 1;
-            other_public = door_public;
-            break;
-        } else if key == door_public {
-            found_private = loop_num + 1;
-            other_public = card_public;
-            break;
-        }
-    }
-    let mut shared = Transform::new(other_public, 20201227);
-    let mut shared_secret = 0;
-    for _ in 0..found_private {
-        shared_secret = shared.next().unwrap();
-        //println!("{}", shared_secret);
-    }
+    // Both sides should agree on the secret
+    debug_assert_eq!(
+        Some(shared_secret),
+        mod_pow(card_public, loop_size(door_public), DIVISOR)
+    );
     println!("{}", shared_secret);
 }
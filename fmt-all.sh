@@ -2,7 +2,7 @@
 
 set -xe
 
-for i in day-* commons; do (
+for i in day-* commons console; do (
 	cd $i;
 	cargo fmt
 ); done